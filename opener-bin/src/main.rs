@@ -6,6 +6,7 @@
     unused_qualifications
 )]
 
+use opener::OpenOptions;
 use std::path::PathBuf;
 use std::process;
 use structopt::StructOpt;
@@ -23,22 +24,39 @@ struct Args {
     /// Reveal the file in the file explorer instead of opening it
     #[structopt(long = "reveal", short = "R", conflicts_with = "browser")]
     reveal: bool,
+
+    /// Open the path with the given application instead of the default one
+    #[structopt(long = "with", conflicts_with_all = &["browser", "reveal"])]
+    with: Option<String>,
 }
 
 fn main() {
     let args = Args::from_args();
 
-    let open_result = if args.browser {
-        opener::open_browser(&args.path)
+    let options = OpenOptions::new();
+    let open_result = if let Some(app) = &args.with {
+        options.open_with(&args.path, app).map(Some)
+    } else if args.browser {
+        options.open_browser(&args.path).map(Some)
     } else if args.reveal {
-        opener::reveal(&args.path)
+        options.reveal(&args.path).map(|()| None)
     } else {
-        opener::open(&args.path)
+        options.open(&args.path).map(Some)
     };
 
     match open_result {
-        Ok(()) => {
+        Ok(launched) => {
             println!("Opened path successfully.");
+            if let Some(launched) = launched {
+                print!("launcher: {}", launched.launcher());
+                if let Some(pid) = launched.pid() {
+                    print!(" (pid {pid})");
+                }
+                if !launched.is_confirmed() {
+                    print!(", not waited for");
+                }
+                println!();
+            }
         }
         Err(e) => {
             println!("Failed to open path.\n\nerror:\n\n{e:#?}");
@@ -0,0 +1,44 @@
+use crate::{OpenError, OpenOptions};
+use std::ffi::OsStr;
+
+/// An application that can open a path. Returned by [`applications_for`](crate::applications_for).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Application {
+    id: String,
+    name: String,
+    icon: Option<String>,
+}
+
+impl Application {
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    pub(crate) fn new(id: String, name: String, icon: Option<String>) -> Self {
+        Self { id, name, icon }
+    }
+
+    /// The identifier of the application, which can be passed to
+    /// [`open_with`](crate::open_with). On Linux, this is the desktop file ID, such as
+    /// `org.gnome.TextEditor.desktop`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The display name of the application, localized when possible.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The icon of the application: an icon theme name, or an absolute path.
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    /// Opens a file or link with the application. This is a shorthand for
+    /// `OpenOptions::new().open_with(path, application.id())`; use
+    /// [`OpenOptions::open_with`] to configure the launch.
+    pub fn open<P>(&self, path: P) -> Result<(), OpenError>
+    where
+        P: AsRef<OsStr>,
+    {
+        OpenOptions::new().open_with(path, &self.id).map(|_| ())
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{Launched, OpenError, OpenOptions};
+use std::ffi::OsStr;
+use std::fmt::Debug;
+use std::path::Path;
+
+/// A custom way of opening paths, such as a launcher program that a managed desktop requires.
+///
+/// Register one with [`OpenOptions::backend`]. It's tried before the built-in launchers, as
+/// [`Launcher::Custom`](crate::Launcher::Custom) with its [`name`](Backend::name), unless the order
+/// is changed with [`OpenOptions::launchers`]:
+///
+/// ```no_run
+/// # fn main() -> Result<(), ::opener::OpenError> {
+/// use opener::{Backend, Launched, Launcher, OpenError, OpenOptions};
+/// use std::ffi::OsStr;
+/// use std::process::Command;
+///
+/// #[derive(Debug)]
+/// struct CorporateLauncher;
+///
+/// impl Backend for CorporateLauncher {
+///     fn name(&self) -> &str {
+///         "corp-open"
+///     }
+///
+///     fn open(&self, path: &OsStr, _options: &OpenOptions) -> Result<Launched, OpenError> {
+///         let status = Command::new("corp-open")
+///             .arg(path)
+///             .status()
+///             .map_err(OpenError::Io)?;
+///         if !status.success() {
+///             return Err(OpenError::ExitStatus {
+///                 cmd: "corp-open".into(),
+///                 status,
+///                 stderr: String::new(),
+///             });
+///         }
+///         Ok(Launched::new(
+///             Launcher::Custom(self.name().to_owned()),
+///             None,
+///             true,
+///         ))
+///     }
+/// }
+///
+/// OpenOptions::new()
+///     .backend(CorporateLauncher)
+///     .open("report.pdf")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// If a backend fails, the next launcher is tried, unless the error is
+/// [`OpenError::Cancelled`].
+pub trait Backend: Debug + Send + Sync {
+    /// The name of the backend, which identifies it as [`Launcher::Custom`](crate::Launcher::Custom)
+    /// and in errors. It should be unique among the registered backends.
+    fn name(&self) -> &str;
+
+    /// Whether the backend can be used right now, such as whether its program is installed.
+    /// Unavailable backends are skipped. The default implementation returns `true`.
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Opens a file or link.
+    fn open(&self, path: &OsStr, options: &OpenOptions) -> Result<Launched, OpenError>;
+
+    /// Reveals files or folders in the file manager, for `OpenOptions::reveal` and
+    /// `OpenOptions::reveal_many` with the **reveal** feature. Backends that return
+    /// [`OpenError::Unsupported`], as the default implementation does, are skipped.
+    ///
+    /// Backends are tried before the platform's built-in launchers, or in the order set with
+    /// `OpenOptions::reveal_launchers`, which can also leave the built-in ones out.
+    fn reveal(&self, paths: &[&Path], options: &OpenOptions) -> Result<(), OpenError> {
+        let _ = (paths, options);
+        Err(OpenError::Unsupported {
+            operation: "revealing paths",
+        })
+    }
+
+    /// Opens a folder in the file manager, for `OpenOptions::open_folder` with the **reveal**
+    /// feature. Backends are tried as for [`reveal`](Backend::reveal), and are likewise skipped
+    /// if they return [`OpenError::Unsupported`], as the default implementation does.
+    fn open_folder(&self, path: &Path, options: &OpenOptions) -> Result<(), OpenError> {
+        let _ = (path, options);
+        Err(OpenError::Unsupported {
+            operation: "opening a folder in the file manager",
+        })
+    }
+}
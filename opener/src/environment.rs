@@ -0,0 +1,309 @@
+use std::sync::OnceLock;
+
+/// A snapshot of the runtime environment that affects how paths are opened: Windows Subsystem for
+/// Linux, containers, application sandboxes or packaging formats, and whether there's a graphical
+/// session.
+///
+/// [`Environment::current`] detects it once and caches it for the lifetime of the process. Tests,
+/// or programs that know better, can build their own with [`Environment::new`] and the setters,
+/// and pass it to [`OpenOptions::environment`](crate::OpenOptions::environment):
+///
+/// ```no_run
+/// # fn main() -> Result<(), ::opener::OpenError> {
+/// use opener::{Environment, OpenOptions, WslVersion};
+///
+/// println!("{:?}", Environment::current());
+///
+/// // Behave as if running under WSL 2.
+/// let mut environment = Environment::new();
+/// environment.set_wsl(Some(WslVersion::Wsl2));
+/// OpenOptions::new()
+///     .environment(environment)
+///     .open("https://www.rust-lang.org")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Detection only happens on Linux; on other platforms, the current environment has nothing set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Environment {
+    wsl: Option<WslVersion>,
+    container: Option<Container>,
+    package: Option<Package>,
+    headless: bool,
+}
+
+/// A version of Windows Subsystem for Linux.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WslVersion {
+    /// WSL 1, which translates Linux system calls.
+    Wsl1,
+
+    /// WSL 2, which runs a Linux kernel in a virtual machine.
+    Wsl2,
+}
+
+/// A container runtime.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Container {
+    /// Docker, detected by `/.dockerenv` or the control groups of the process.
+    Docker,
+
+    /// Podman, or tools built on it such as Toolbox, detected by `/run/.containerenv`.
+    Podman,
+
+    /// LXC or LXD.
+    Lxc,
+
+    /// `systemd-nspawn`.
+    SystemdNspawn,
+
+    /// A Kubernetes pod, detected by `$KUBERNETES_SERVICE_HOST`.
+    Kubernetes,
+
+    /// Another container manager, named by `/run/systemd/container` or `$container`.
+    Other(String),
+}
+
+/// An application sandbox or packaging format the process runs from.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Package {
+    /// A Flatpak sandbox, detected by `/.flatpak-info`.
+    Flatpak,
+
+    /// A Snap package, detected by `$SNAP`.
+    Snap,
+
+    /// An AppImage, detected by `$APPIMAGE`. AppImages aren't sandboxed.
+    AppImage,
+}
+
+impl Environment {
+    /// Creates an environment with nothing detected: not WSL, not in a container, not packaged, and
+    /// not headless.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The environment of the current process, detected on first use and then cached.
+    pub fn current() -> &'static Environment {
+        static CURRENT: OnceLock<Environment> = OnceLock::new();
+        CURRENT.get_or_init(Environment::detect)
+    }
+
+    /// Detects the environment of the current process, without caching.
+    pub fn detect() -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            let container = detect::container();
+            Self {
+                // WSL interop isn't available from containers, even when they run under WSL.
+                wsl: container.is_none().then(detect::wsl).flatten(),
+                container,
+                package: detect::package(),
+                headless: detect::headless(),
+            }
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        Self::default()
+    }
+
+    /// The version of Windows Subsystem for Linux the process runs under, if any.
+    pub fn wsl(&self) -> Option<WslVersion> {
+        self.wsl
+    }
+
+    /// Whether the process runs under Windows Subsystem for Linux, in which case paths are opened
+    /// with Windows programs.
+    pub fn is_wsl(&self) -> bool {
+        self.wsl.is_some()
+    }
+
+    /// The container the process runs in, if any.
+    pub fn container(&self) -> Option<&Container> {
+        self.container.as_ref()
+    }
+
+    /// The sandbox or packaging format the process runs from, if any.
+    pub fn package(&self) -> Option<Package> {
+        self.package
+    }
+
+    /// Whether the process runs in a Flatpak or Snap sandbox, where the desktop portal is the way
+    /// to open paths outside of it.
+    pub fn is_sandboxed(&self) -> bool {
+        matches!(self.package, Some(Package::Flatpak | Package::Snap))
+    }
+
+    /// Whether there's no graphical session, because none of the `DISPLAY`, `WAYLAND_DISPLAY` and
+    /// `WAYLAND_SOCKET` environment variables is set, such as in an SSH session. Launchers such as
+    /// `xdg-open` then have no graphical application to open paths with.
+    pub fn is_headless(&self) -> bool {
+        self.headless
+    }
+
+    /// Sets the WSL version.
+    pub fn set_wsl(&mut self, wsl: Option<WslVersion>) -> &mut Self {
+        self.wsl = wsl;
+        self
+    }
+
+    /// Sets the container.
+    pub fn set_container(&mut self, container: Option<Container>) -> &mut Self {
+        self.container = container;
+        self
+    }
+
+    /// Sets the sandbox or packaging format.
+    pub fn set_package(&mut self, package: Option<Package>) -> &mut Self {
+        self.package = package;
+        self
+    }
+
+    /// Sets whether there's no graphical session.
+    pub fn set_headless(&mut self, headless: bool) -> &mut Self {
+        self.headless = headless;
+        self
+    }
+}
+
+/// Tells whether and which WSL runs the process from the kernel release and version, and whether
+/// `$WSL_DISTRO_NAME` and `$WSL_INTEROP` are set.
+///
+/// WSL 2 kernels are named `*-microsoft-standard*` (or `*-WSL2`), while WSL 1 reports the Windows
+/// build, such as `4.4.0-19041-Microsoft`. WSL with a custom kernel that doesn't mention Microsoft
+/// is still revealed by `$WSL_DISTRO_NAME`, and `$WSL_INTEROP` then tells the versions apart.
+#[cfg(target_os = "linux")]
+fn wsl_version(kernel: &str, distro_name: bool, interop: bool) -> Option<WslVersion> {
+    let kernel = kernel.to_ascii_lowercase();
+    if kernel.contains("wsl2") || kernel.contains("microsoft-standard") {
+        Some(WslVersion::Wsl2)
+    } else if kernel.contains("microsoft") {
+        Some(WslVersion::Wsl1)
+    } else if distro_name {
+        Some(if interop {
+            WslVersion::Wsl2
+        } else {
+            WslVersion::Wsl1
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod detect {
+    use super::{Container, Package, WslVersion};
+    use std::path::Path;
+    use std::{env, fs};
+
+    fn var(name: &str) -> Option<String> {
+        env::var(name).ok().filter(|value| !value.is_empty())
+    }
+
+    /// Uses the kernel release and version, which mention Microsoft under WSL, and
+    /// `$WSL_DISTRO_NAME`, which WSL sets even when running a custom kernel.
+    pub(super) fn wsl() -> Option<WslVersion> {
+        let kernel = ["/proc/sys/kernel/osrelease", "/proc/version"]
+            .into_iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .collect::<Vec<_>>()
+            .join("\n");
+        super::wsl_version(
+            &kernel,
+            var("WSL_DISTRO_NAME").is_some(),
+            var("WSL_INTEROP").is_some(),
+        )
+    }
+
+    pub(super) fn container() -> Option<Container> {
+        if var("KUBERNETES_SERVICE_HOST").is_some() {
+            return Some(Container::Kubernetes);
+        }
+
+        // Written by systemd, or set by the container manager for PID 1.
+        let manager = fs::read_to_string("/run/systemd/container")
+            .ok()
+            .map(|manager| manager.trim().to_owned())
+            .filter(|manager| !manager.is_empty())
+            .or_else(|| var("container"));
+        if let Some(manager) = manager {
+            return Some(match manager.as_str() {
+                "docker" => Container::Docker,
+                "podman" | "oci" => Container::Podman,
+                "lxc" | "lxc-libvirt" => Container::Lxc,
+                "systemd-nspawn" => Container::SystemdNspawn,
+                _ => Container::Other(manager),
+            });
+        }
+
+        if Path::new("/run/.containerenv").exists() {
+            return Some(Container::Podman);
+        }
+
+        let docker_cgroup = fs::read_to_string("/proc/self/cgroup")
+            .is_ok_and(|cgroup| cgroup.to_ascii_lowercase().contains("docker"));
+        if Path::new("/.dockerenv").exists() || docker_cgroup {
+            return Some(Container::Docker);
+        }
+
+        None
+    }
+
+    pub(super) fn package() -> Option<Package> {
+        if Path::new("/.flatpak-info").exists() {
+            Some(Package::Flatpak)
+        } else if var("SNAP").is_some() {
+            Some(Package::Snap)
+        } else if var("APPIMAGE").is_some() {
+            Some(Package::AppImage)
+        } else {
+            None
+        }
+    }
+
+    pub(super) fn headless() -> bool {
+        ["DISPLAY", "WAYLAND_DISPLAY", "WAYLAND_SOCKET"]
+            .into_iter()
+            .all(|name| var(name).is_none())
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::{wsl_version, WslVersion};
+
+    #[test]
+    fn wsl_from_kernel() {
+        let wsl2 = "5.15.153.1-microsoft-standard-WSL2";
+        let wsl1 = "4.4.0-19041-Microsoft";
+        let native = "6.8.0-45-generic";
+        assert_eq!(wsl_version(wsl2, false, false), Some(WslVersion::Wsl2));
+        assert_eq!(wsl_version(wsl1, false, false), Some(WslVersion::Wsl1));
+        assert_eq!(wsl_version(native, false, false), None);
+
+        // The kernel names the version even if the variables suggest otherwise.
+        assert_eq!(wsl_version(wsl1, true, true), Some(WslVersion::Wsl1));
+        assert_eq!(wsl_version(wsl2, true, false), Some(WslVersion::Wsl2));
+    }
+
+    #[test]
+    fn wsl_from_variables() {
+        let custom = "6.6.36-custom\nLinux version 6.6.36-custom (gcc 13.2.0)";
+        assert_eq!(wsl_version(custom, true, true), Some(WslVersion::Wsl2));
+        assert_eq!(wsl_version(custom, true, false), Some(WslVersion::Wsl1));
+        assert_eq!(wsl_version(custom, false, true), None);
+        assert_eq!(wsl_version("", true, true), Some(WslVersion::Wsl2));
+    }
+
+    #[test]
+    fn wsl_from_version_when_release_is_plain() {
+        // `/proc/sys/kernel/osrelease` followed by `/proc/version`.
+        let kernel = "4.4.0\nLinux version 4.4.0-19041-Microsoft (Microsoft@Microsoft.com)";
+        assert_eq!(wsl_version(kernel, false, false), Some(WslVersion::Wsl1));
+    }
+}
@@ -6,8 +6,13 @@
 //! * Weird paths work: paths with spaces, unicode characters, non-unicode characters (e.g. `"\u{01}"`)
 //! * Path to non-existent file generates an error for both implementations.
 
-use crate::OpenError;
+use crate::{OpenError, OpenErrorKind, OpenOptions};
 use std::collections::HashMap;
+#[cfg(feature = "portal")]
+use std::ffi::OsStr;
+#[cfg(feature = "portal")]
+use std::fs;
+#[cfg(feature = "reveal")]
 use std::fs::File;
 use std::os::fd::AsFd;
 use std::path::Path;
@@ -15,32 +20,209 @@ use std::{error, fmt, io};
 use url::Url;
 use zbus::blocking::Connection;
 
-// We should prefer the OpenURI interface, because it correctly handles runtimes such as Flatpak.
-// However, OpenURI was broken in the original version of the interface (it did not highlight the items).
-// This version is still in use by some distributions, which would result in degraded functionality for some users.
-// That's why we're first trying to use the FileManager1 interface, falling back to the OpenURI interface.
-// Source: https://chromium-review.googlesource.com/c/chromium/src/+/3009959
-pub(crate) fn reveal_with_dbus(path: &Path) -> Result<(), OpenError> {
-    let connection = Connection::session().map_err(dbus_to_open_error)?;
-    reveal_with_filemanager1(path, &connection)
-        .or_else(|_| reveal_with_open_uri_portal(path, &connection))
+#[cfg(feature = "reveal")]
+pub(crate) fn reveal_with_filemanager1(
+    paths: &[&Path],
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    let connection = Connection::session().map_err(session_bus_error)?;
+    let uris = paths
+        .iter()
+        .map(|path| path_to_uri(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let proxy = FileManager1Proxy::new(&connection).map_err(dbus_to_open_error)?;
+    proxy
+        .show_items(&uris, options.startup_id().unwrap_or_default())
+        .map_err(dbus_to_open_error)
 }
 
-fn reveal_with_filemanager1(path: &Path, connection: &Connection) -> Result<(), OpenError> {
+#[cfg(feature = "reveal")]
+pub(crate) fn show_folder_with_filemanager1(
+    path: &Path,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
     let uri = path_to_uri(path)?;
-    let proxy = FileManager1Proxy::new(connection).map_err(dbus_to_open_error)?;
-    proxy.show_items(&[uri], "").map_err(dbus_to_open_error)
+    let connection = Connection::session().map_err(session_bus_error)?;
+    let proxy = FileManager1Proxy::new(&connection).map_err(dbus_to_open_error)?;
+    proxy
+        .show_folders(&[uri], options.startup_id().unwrap_or_default())
+        .map_err(dbus_to_open_error)
 }
 
-fn reveal_with_open_uri_portal(path: &Path, connection: &Connection) -> Result<(), OpenError> {
-    let file = File::open(path).map_err(OpenError::Io)?;
-    let proxy = OpenURIProxy::new(connection).map_err(dbus_to_open_error)?;
+/// There's no fallback for showing properties, so failing to reach a file manager that implements
+/// `ShowItemProperties` is reported as [`OpenError::Unsupported`].
+#[cfg(feature = "reveal")]
+pub(crate) fn show_properties_with_dbus(
+    path: &Path,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    const UNSUPPORTED: OpenError = OpenError::Unsupported {
+        operation: "showing file properties",
+    };
+
+    let uri = path_to_uri(path)?;
+    let connection = Connection::session().map_err(|_| UNSUPPORTED)?;
+    let proxy = FileManager1Proxy::new(&connection).map_err(|_| UNSUPPORTED)?;
     proxy
-        .open_directory("", file.as_fd().into(), HashMap::new())
-        .map_err(dbus_to_open_error)
-        .map(|_| ())
+        .show_item_properties(&[uri], options.startup_id().unwrap_or_default())
+        .map_err(|error| match error {
+            zbus::Error::MethodError(name, _, _)
+                if matches!(
+                    name.as_str(),
+                    "org.freedesktop.DBus.Error.ServiceUnknown"
+                        | "org.freedesktop.DBus.Error.NameHasNoOwner"
+                        | "org.freedesktop.DBus.Error.UnknownMethod"
+                        | "org.freedesktop.DBus.Error.UnknownInterface"
+                        | "org.freedesktop.DBus.Error.UnknownObject"
+                ) =>
+            {
+                UNSUPPORTED
+            }
+            error => dbus_to_open_error(error),
+        })
+}
+
+/// `OpenDirectory` shows the folder containing a file, so it's called once per folder.
+#[cfg(feature = "reveal")]
+pub(crate) fn reveal_with_open_uri_portal(
+    paths: &[&Path],
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    let connection = Connection::session().map_err(session_bus_error)?;
+    let proxy = OpenURIProxy::new(&connection).map_err(dbus_to_open_error)?;
+    let parent_window = options.parent_window_id();
+    let wait = options.should_wait(false);
+    let token_value = options.startup_id().map(zbus::zvariant::Value::from);
+    let mut portal_options = HashMap::new();
+    if let Some(token_value) = &token_value {
+        portal_options.insert("activation_token", token_value);
+    }
+    for group in crate::group_by_parent(paths) {
+        let file = File::open(group[0]).map_err(OpenError::Io)?;
+        portal_request(&connection, portal_options.clone(), wait, |options| {
+            proxy.open_directory(&parent_window, file.as_fd().into(), options)
+        })?;
+    }
+    Ok(())
+}
+
+/// Opens a file or link through the `org.freedesktop.portal.OpenURI` portal, which works from
+/// inside Flatpak and Snap sandboxes. Links are passed to `OpenURI`, while files, including `file:`
+/// URLs, are opened and passed as a file descriptor to `OpenFile`, so that the application that
+/// opens them outside of the sandbox is granted access.
+///
+/// With `ask`, the portal lets the user choose the application. The file is opened for writing as
+/// well when [`OpenOptions::writable`] is set, so that the portal can grant write access.
+///
+/// The portal's response is waited for if `wait` is set, in which case a cancelled request is
+/// reported as [`OpenError::Cancelled`].
+#[cfg(feature = "portal")]
+pub(crate) fn open_with_portal(
+    path: &OsStr,
+    ask: bool,
+    wait: bool,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    let connection = Connection::session().map_err(session_bus_error)?;
+    let proxy = OpenURIProxy::new(&connection).map_err(dbus_to_open_error)?;
+    let parent_window = options.parent_window_id();
+
+    let writable = options.should_allow_writes();
+    let ask_value = zbus::zvariant::Value::from(ask);
+    let writable_value = zbus::zvariant::Value::from(writable);
+    let mut portal_options = HashMap::new();
+    if ask {
+        portal_options.insert("ask", &ask_value);
+    }
+    if writable {
+        portal_options.insert("writable", &writable_value);
+    }
+    let token_value = options.startup_id().map(zbus::zvariant::Value::from);
+    if let Some(token_value) = &token_value {
+        portal_options.insert("activation_token", token_value);
+    }
+
+    // Like xdg-open, anything that parses as an absolute URL is treated as one.
+    let path = match path.to_str().and_then(|path| Url::parse(path).ok()) {
+        Some(url) if url.scheme() != "file" => {
+            return portal_request(&connection, portal_options, wait, |options| {
+                proxy.open_uri(&parent_window, url.as_str(), options)
+            });
+        }
+        Some(url) => url.to_file_path().map_err(|_| uri_to_open_error())?,
+        None => match options.current_dir_path() {
+            Some(dir) if Path::new(path).is_relative() => dir.join(path),
+            _ => Path::new(path).to_owned(),
+        },
+    };
+
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(writable && !path.is_dir())
+        .open(&path)
+        .map_err(OpenError::Io)?;
+    portal_request(&connection, portal_options, wait, |options| {
+        proxy.open_file(&parent_window, file.as_fd().into(), options)
+    })
 }
 
+/// Makes a portal method call, then, if `wait` is set, waits for the `Response` signal of the
+/// `org.freedesktop.portal.Request` object it returns, which is only sent once the request has
+/// been handled, such as after the user chose an application. The portal may take arbitrarily
+/// long to respond, or never respond at all if its backend is broken, so this is only done when
+/// asked for.
+///
+/// The signal may be sent before the call returns, so to not miss it, the `Response` signals of all
+/// requests are subscribed to before the call, and the one of the returned object path is picked.
+fn portal_request<F>(
+    connection: &Connection,
+    options: HashMap<&str, &zbus::zvariant::Value<'_>>,
+    wait: bool,
+    call: F,
+) -> Result<(), OpenError>
+where
+    F: FnOnce(
+        HashMap<&str, &zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>,
+{
+    if !wait {
+        return call(options).map(|_| ()).map_err(dbus_to_open_error);
+    }
+
+    let rule = zbus::MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .interface("org.freedesktop.portal.Request")
+        .and_then(|rule| rule.member("Response"))
+        .and_then(|rule| rule.path_namespace("/org/freedesktop/portal/desktop/request"))
+        .map_err(dbus_to_open_error)?
+        .build();
+    let mut messages = zbus::blocking::MessageIterator::for_match_rule(rule, connection, None)
+        .map_err(dbus_to_open_error)?;
+
+    let path = call(options).map_err(dbus_to_open_error)?;
+
+    let response = messages.find_map(|message| {
+        let message = message.ok()?;
+        let header = message.header();
+        if header.path()?.as_str() != path.as_str() {
+            return None;
+        }
+        Response::from_message(message)
+    });
+    let response = response.ok_or_else(|| {
+        dbus_to_open_error(zbus::Error::Failure(
+            "the connection closed before the portal responded".into(),
+        ))
+    })?;
+
+    match response.args().map_err(dbus_to_open_error)?.response {
+        0 => Ok(()),
+        1 => Err(OpenError::Cancelled),
+        _ => Err(OpenError::PortalFailed),
+    }
+}
+
+#[cfg(feature = "reveal")]
 fn path_to_uri(path: &Path) -> Result<Url, OpenError> {
     let path = path.canonicalize().map_err(OpenError::Io)?;
     Url::from_file_path(path).map_err(|_| uri_to_open_error())
@@ -53,8 +235,65 @@ fn uri_to_open_error() -> OpenError {
     ))
 }
 
+/// Classifies a D-Bus error, so that a missing service or a denied request are reported as such by
+/// [`OpenError::kind`].
 fn dbus_to_open_error(error: zbus::Error) -> OpenError {
-    OpenError::Io(io::Error::other(error))
+    let (kind, hint) = match error_name(&error) {
+        Some((name, message)) => dbus_error_kind(&name, message.as_deref()),
+        None => (OpenErrorKind::Other, None),
+    };
+    OpenError::Io(crate::classified_io_error(error, kind, hint))
+}
+
+/// The name and message of the error reply to a method call.
+fn error_name(error: &zbus::Error) -> Option<(String, Option<String>)> {
+    use zbus::DBusError;
+
+    match error {
+        zbus::Error::MethodError(name, message, _) => {
+            Some((name.as_str().to_owned(), message.clone()))
+        }
+        zbus::Error::FDO(error) => Some((
+            error.name().as_str().to_owned(),
+            error.description().map(str::to_owned),
+        )),
+        _ => None,
+    }
+}
+
+fn dbus_error_kind(name: &str, message: Option<&str>) -> (OpenErrorKind, Option<&'static str>) {
+    match name {
+        "org.freedesktop.DBus.Error.ServiceUnknown"
+        | "org.freedesktop.DBus.Error.NameHasNoOwner" => {
+            let message = message.unwrap_or_default();
+            let hint = if message.contains("org.freedesktop.portal.") {
+                Some("install xdg-desktop-portal, and a portal backend for your desktop")
+            } else if message.contains("org.freedesktop.FileManager1") {
+                Some(
+                    "install a file manager that implements org.freedesktop.FileManager1, such as \
+                     Nautilus, Dolphin or Nemo",
+                )
+            } else {
+                None
+            };
+            (OpenErrorKind::LauncherMissing, hint)
+        }
+        "org.freedesktop.DBus.Error.AccessDenied" => (OpenErrorKind::PermissionDenied, None),
+        _ => (OpenErrorKind::Other, None),
+    }
+}
+
+/// Failing to connect to the session bus usually means that there's no graphical session, such as
+/// over SSH.
+fn session_bus_error(error: zbus::Error) -> OpenError {
+    OpenError::Io(crate::classified_io_error(
+        error,
+        OpenErrorKind::NoDisplay,
+        Some(
+            "no D-Bus session bus found; are you in an SSH session? Run the program from a \
+             graphical session, or set DBUS_SESSION_BUS_ADDRESS",
+        ),
+    ))
 }
 
 #[derive(Debug)]
@@ -69,6 +308,7 @@ impl fmt::Display for FilePathToUriError {
 impl error::Error for FilePathToUriError {}
 
 /// # D-Bus interface proxy for `org.freedesktop.FileManager1` interface.
+#[cfg(feature = "reveal")]
 #[zbus::proxy(
     gen_async = false,
     interface = "org.freedesktop.FileManager1",
@@ -76,8 +316,14 @@ impl error::Error for FilePathToUriError {}
     default_path = "/org/freedesktop/FileManager1"
 )]
 trait FileManager1 {
+    /// ShowFolders method
+    fn show_folders(&self, uris: &[Url], startup_id: &str) -> zbus::Result<()>;
+
     /// ShowItems method
     fn show_items(&self, uris: &[Url], startup_id: &str) -> zbus::Result<()>;
+
+    /// ShowItemProperties method
+    fn show_item_properties(&self, uris: &[Url], startup_id: &str) -> zbus::Result<()>;
 }
 
 /// # D-Bus interface proxy for: `org.freedesktop.portal.OpenURI`
@@ -88,6 +334,23 @@ trait FileManager1 {
     default_path = "/org/freedesktop/portal/desktop"
 )]
 pub trait OpenURI {
+    /// OpenURI method
+    #[zbus(name = "OpenURI")]
+    fn open_uri(
+        &self,
+        parent_window: &str,
+        uri: &str,
+        options: HashMap<&str, &zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
+    /// OpenFile method
+    fn open_file(
+        &self,
+        parent_window: &str,
+        fd: zbus::zvariant::Fd<'_>,
+        options: HashMap<&str, &zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
+
     /// OpenDirectory method
     fn open_directory(
         &self,
@@ -96,3 +359,83 @@ pub trait OpenURI {
         options: HashMap<&str, &zbus::zvariant::Value<'_>>,
     ) -> zbus::Result<zbus::zvariant::OwnedObjectPath>;
 }
+
+/// # D-Bus interface proxy for: `org.freedesktop.portal.Request`
+#[zbus::proxy(
+    gen_async = false,
+    interface = "org.freedesktop.portal.Request",
+    default_service = "org.freedesktop.portal.Desktop"
+)]
+trait Request {
+    /// Response signal
+    #[zbus(signal)]
+    fn response(
+        &self,
+        response: u32,
+        results: HashMap<&str, zbus::zvariant::Value<'_>>,
+    ) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dbus_error_kind, dbus_to_open_error, session_bus_error};
+    use crate::OpenErrorKind;
+    use zbus::fdo;
+
+    fn kind(error: fdo::Error) -> OpenErrorKind {
+        dbus_to_open_error(zbus::Error::FDO(Box::new(error))).kind()
+    }
+
+    #[test]
+    fn missing_service() {
+        let error = dbus_to_open_error(zbus::Error::FDO(Box::new(fdo::Error::ServiceUnknown(
+            "The name org.freedesktop.portal.Desktop was not provided by any .service files".into(),
+        ))));
+        assert_eq!(error.kind(), OpenErrorKind::LauncherMissing);
+        assert!(error.hint().unwrap().contains("xdg-desktop-portal"));
+
+        let error = fdo::Error::NameHasNoOwner("org.freedesktop.FileManager1".into());
+        assert_eq!(kind(error), OpenErrorKind::LauncherMissing);
+    }
+
+    #[test]
+    fn method_error_names() {
+        let (kind, hint) = dbus_error_kind(
+            "org.freedesktop.DBus.Error.ServiceUnknown",
+            Some("The name org.freedesktop.FileManager1 was not provided by any .service files"),
+        );
+        assert_eq!(kind, OpenErrorKind::LauncherMissing);
+        assert!(hint.unwrap().contains("FileManager1"));
+
+        let (kind, hint) = dbus_error_kind("org.freedesktop.DBus.Error.NameHasNoOwner", None);
+        assert_eq!((kind, hint), (OpenErrorKind::LauncherMissing, None));
+
+        let (kind, _) = dbus_error_kind("org.freedesktop.DBus.Error.AccessDenied", None);
+        assert_eq!(kind, OpenErrorKind::PermissionDenied);
+
+        let (kind, _) = dbus_error_kind("org.freedesktop.portal.Error.Failed", None);
+        assert_eq!(kind, OpenErrorKind::Other);
+    }
+
+    #[test]
+    fn access_denied() {
+        let error = fdo::Error::AccessDenied("Portal operation not allowed".into());
+        assert_eq!(kind(error), OpenErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn other_errors() {
+        assert_eq!(kind(fdo::Error::Failed("".into())), OpenErrorKind::Other);
+        let error = dbus_to_open_error(zbus::Error::Failure("connection closed".into()));
+        assert_eq!(error.kind(), OpenErrorKind::Other);
+    }
+
+    #[test]
+    fn missing_session_bus() {
+        let error = session_bus_error(zbus::Error::Address(
+            "DBUS_SESSION_BUS_ADDRESS is not set".into(),
+        ));
+        assert_eq!(error.kind(), OpenErrorKind::NoDisplay);
+        assert!(error.hint().unwrap().contains("D-Bus session bus"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Describes how a path was opened. Returned by the methods of [`OpenOptions`](crate::OpenOptions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Launched {
+    launcher: Launcher,
+    pid: Option<u32>,
+    confirmed: bool,
+}
+
+impl Launched {
+    /// Creates a description of how a path was opened, such as by a custom
+    /// [`Backend`](crate::Backend).
+    pub fn new(launcher: Launcher, pid: Option<u32>, confirmed: bool) -> Self {
+        Self {
+            launcher,
+            pid,
+            confirmed,
+        }
+    }
+
+    /// The launcher that handled the request.
+    pub fn launcher(&self) -> &Launcher {
+        &self.launcher
+    }
+
+    /// The process ID of the launcher process, if one was spawned.
+    ///
+    /// Note that this is the process that was started by this library, such as `xdg-open`, which
+    /// isn't necessarily the program that ends up displaying the path.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Whether the launcher confirmed that it succeeded.
+    ///
+    /// This is `true` when the launcher process was waited for and exited successfully, or when the
+    /// launcher reports success directly, as `ShellExecuteW` does. It's `false` when the request
+    /// was only dispatched, i.e. the launcher process was started but not waited for.
+    pub fn is_confirmed(&self) -> bool {
+        self.confirmed
+    }
+}
+
+/// A program or mechanism used to open a path.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Launcher {
+    /// The system `xdg-open` script.
+    XdgOpen,
+
+    /// The `xdg-open` script embedded in this library, run with `sh`.
+    EmbeddedXdgOpen,
+
+    /// This library's own implementation of `xdg-open`, which looks up the default application in
+    /// `mimeapps.list` and launches it directly. Requires the **native-xdg** feature.
+    Native,
+
+    /// `wslview`, from [`wslu`](https://github.com/wslutilities/wslu/), on Windows Subsystem for
+    /// Linux.
+    WslView,
+
+    /// The `org.freedesktop.portal.OpenURI` desktop portal, used first inside Flatpak and Snap
+    /// sandboxes. Requires the **portal** feature, or the **reveal** feature for revealing paths
+    /// and opening folders.
+    Portal,
+
+    /// The `org.freedesktop.FileManager1` D-Bus interface of file managers, used to reveal paths
+    /// and open folders on Linux. Requires the **reveal** feature.
+    FileManager1,
+
+    /// Opening the folders that contain the paths to reveal with [`open`](crate::open), the last
+    /// resort for revealing paths on Linux and other platforms using `xdg-open`. Requires the
+    /// **reveal** feature.
+    ContainingFolder,
+
+    /// Windows File Explorer, used to reveal paths and open folders on Windows and Windows
+    /// Subsystem for Linux. Requires the **reveal** feature.
+    Explorer,
+
+    /// The macOS `open` command.
+    MacOpen,
+
+    /// The Windows `ShellExecuteW` API function.
+    ShellExecute,
+
+    /// The program named by the `BROWSER` environment variable.
+    Browser,
+
+    /// The `Exec` command line of a desktop entry, used by [`open_with`](crate::open_with).
+    DesktopEntry,
+
+    /// An executable, used by [`open_with`](crate::open_with).
+    Executable,
+
+    /// A custom [`Backend`](crate::Backend) registered with
+    /// [`OpenOptions::backend`](crate::OpenOptions::backend), by its name.
+    Custom(String),
+}
+
+impl Launcher {
+    /// Whether this built-in launcher can be used on the current platform.
+    pub(crate) fn is_supported(&self) -> bool {
+        if cfg!(target_os = "windows") {
+            *self == Launcher::ShellExecute
+        } else if cfg!(target_os = "macos") {
+            *self == Launcher::MacOpen
+        } else {
+            matches!(
+                self,
+                Launcher::XdgOpen | Launcher::EmbeddedXdgOpen | Launcher::WslView
+            ) || (*self == Launcher::Native && cfg!(feature = "native-xdg"))
+                || (*self == Launcher::Portal && cfg!(all(feature = "portal", target_os = "linux")))
+        }
+    }
+
+    /// Whether this built-in launcher can reveal paths and open folders on the current platform.
+    #[cfg(feature = "reveal")]
+    pub(crate) fn can_reveal(&self) -> bool {
+        if cfg!(target_os = "windows") {
+            *self == Launcher::Explorer
+        } else if cfg!(target_os = "macos") {
+            *self == Launcher::MacOpen
+        } else if cfg!(target_os = "linux") {
+            matches!(
+                self,
+                Launcher::FileManager1
+                    | Launcher::Portal
+                    | Launcher::ContainingFolder
+                    | Launcher::Explorer
+            )
+        } else {
+            *self == Launcher::ContainingFolder
+        }
+    }
+}
+
+impl Display for Launcher {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Launcher::XdgOpen => "xdg-open",
+            Launcher::EmbeddedXdgOpen => "embedded xdg-open script",
+            Launcher::Native => "native xdg-open",
+            Launcher::WslView => "wslview",
+            Launcher::Portal => "desktop portal",
+            Launcher::FileManager1 => "FileManager1",
+            Launcher::ContainingFolder => "containing folder",
+            Launcher::Explorer => "File Explorer",
+            Launcher::MacOpen => "open",
+            Launcher::ShellExecute => "ShellExecuteW",
+            Launcher::Browser => "$BROWSER",
+            Launcher::DesktopEntry => "desktop entry",
+            Launcher::Executable => "executable",
+            Launcher::Custom(name) => name,
+        };
+        f.write_str(name)
+    }
+}
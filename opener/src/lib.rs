@@ -14,13 +14,41 @@
 //! # }
 //! ```
 //!
+//! To open a path with a specific application instead, use [`open_with`].
+//!
 //! An [`open_browser`] function is also provided, for when you intend on opening a file or link in
 //! a browser, specifically. This function works like the [`open`] function, but explicitly allows
 //! overriding the browser launched by setting the `$BROWSER` environment variable.
 //!
+//! To change the working directory or environment of the launcher, wait for it to exit, or prefer a
+//! particular launcher, use [`OpenOptions`]. Its methods also return a [`Launched`] value, which
+//! records which launcher handled the request. Custom launchers can be added by implementing
+//! [`Backend`], and the built-in ones reordered or disabled.
+//!
+//! The runtime environment that backend decisions depend on, such as WSL or a Flatpak sandbox, is
+//! available as an [`Environment`].
+//!
+//! On Linux and other platforms using `xdg-open`, [`DesktopEntry`] parses the `.desktop` files
+//! that describe installed applications, and expands their `Exec` command lines.
+//! [`set_default_application`] changes the default application for a MIME type or URL scheme, and
+//! [`UrlSchemeHandler`] registers an application as the handler of a custom URL scheme.
+//! [`desktop_environment`] detects the desktop environment the way `xdg-open` does.
+//!
 //! # Crate features
 //!
-//! - **reveal** - Enables usage of the [`reveal`] function.
+//! - **reveal** - Enables usage of the [`reveal`], [`reveal_many`], [`open_folder`] and
+//!   [`show_properties`] functions.
+//! - **native-xdg** - On Linux and other platforms using `xdg-open`, makes [`open`] look up the
+//!   default application in `mimeapps.list` and launch it itself, before falling back to `xdg-open`.
+//!   This works on systems without `sh`, and doesn't depend on the desktop environment. The MIME
+//!   type of a file is determined as by [`mime_type`].
+//! - **portal** - On Linux, makes [`open`] use the [`org.freedesktop.portal.OpenURI`] desktop portal
+//!   when running inside a Flatpak or Snap sandbox.
+//! - **raw-window-handle** - Enables [`WindowIdentifier::from_window_handle`], which identifies the
+//!   parent window of portal dialogs from a [`raw-window-handle`] window handle.
+//!
+//! [`org.freedesktop.portal.OpenURI`]: https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.OpenURI.html
+//! [`raw-window-handle`]: https://docs.rs/raw-window-handle
 
 #![warn(
     rust_2018_idioms,
@@ -30,12 +58,18 @@
     unused_qualifications
 )]
 
-#[cfg(all(feature = "reveal", target_os = "linux"))]
+mod application;
+mod backend;
+mod environment;
+#[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
 mod freedesktop;
+mod launched;
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
 mod linux_and_more;
 #[cfg(target_os = "macos")]
 mod macos;
+mod options;
+mod window_identifier;
 #[cfg(target_os = "windows")]
 mod windows;
 
@@ -46,11 +80,26 @@ use crate::macos as sys;
 #[cfg(target_os = "windows")]
 use crate::windows as sys;
 
+pub use crate::application::Application;
+pub use crate::backend::Backend;
+pub use crate::environment::{Container, Environment, Package, WslVersion};
+pub use crate::launched::{Launched, Launcher};
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub use crate::linux_and_more::{
+    desktop_environment, mime_type, set_default_application, DesktopAction, DesktopEntry,
+    DesktopEnvironment, UrlSchemeHandler,
+};
+pub use crate::options::OpenOptions;
+pub use crate::window_identifier::WindowIdentifier;
+
 use std::error::Error;
 use std::ffi::{OsStr, OsString};
 use std::fmt::{self, Display, Formatter};
-use std::process::{Command, ExitStatus, Stdio};
-use std::{env, io};
+use std::io::Read;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use std::{io, thread};
 
 /// Opens a file or link with the system default program.
 ///
@@ -68,15 +117,111 @@ use std::{env, io};
 /// - On Mac the system `open` command is used.
 /// - On Windows Subsystem for Linux (WSL), the system `wslview` from [`wslu`] is used if available,
 ///   otherwise the system `xdg-open` is used, if available.
+/// - On Linux inside a Flatpak or Snap sandbox, with the **portal** feature, the
+///   `org.freedesktop.portal.OpenURI` desktop portal is tried first. Files are passed to it as file
+///   descriptors, so that the application opening them is granted access. With
+///   [`OpenOptions::wait`], the portal's response is waited for, and [`OpenError::Cancelled`] is
+///   returned if the user cancels the request.
 /// - On non-WSL Linux and other platforms, the system `xdg-open` script is used if available,
-///   otherwise an `xdg-open` script embedded in this library is used.
+///   otherwise an `xdg-open` script embedded in this library is used. With the **native-xdg**
+///   feature, this library first looks up the default application itself, following the [XDG MIME
+///   Applications specification], and launches it directly.
 ///
 /// [`wslu`]: https://github.com/wslutilities/wslu/
+/// [XDG MIME Applications specification]: https://specifications.freedesktop.org/mime-apps-spec/latest/
 pub fn open<P>(path: P) -> Result<(), OpenError>
 where
     P: AsRef<OsStr>,
 {
-    sys::open(path.as_ref())
+    OpenOptions::new().open(path).map(|_| ())
+}
+
+/// Opens a file or link with a specific application, instead of the system default program.
+///
+/// `app` identifies the application in a platform-specific way, described below. As with [`open`],
+/// a result of `Ok(())` only means the application was launched; errors beyond that point aren't
+/// caught.
+///
+/// ```no_run
+/// # fn main() -> Result<(), ::opener::OpenError> {
+/// opener::open_with("report.svg", "gimp")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ## Platform Implementation Details
+///
+/// - On Windows, `app` is an executable name or path, launched with the `ShellExecuteW` Windows API
+///   function.
+/// - On Mac, `app` is an application name or path, passed to the system `open -a` command.
+/// - On Linux and other platforms, `app` is either a [desktop file ID] such as `org.gimp.GIMP`
+///   (the `.desktop` suffix is optional), in which case its `Exec` command line is used, or
+///   otherwise an executable name or path.
+///
+/// [desktop file ID]: https://specifications.freedesktop.org/desktop-entry-spec/latest/file-naming.html#desktop-file-id
+pub fn open_with<P, A>(path: P, app: A) -> Result<(), OpenError>
+where
+    P: AsRef<OsStr>,
+    A: AsRef<OsStr>,
+{
+    OpenOptions::new().open_with(path, app).map(|_| ())
+}
+
+/// Lets the user choose an application in a system dialog, then opens a file or link with it.
+///
+/// Through the desktop portal, this blocks until the user picks an application, and
+/// [`OpenError::Cancelled`] is returned if they close the dialog instead. Use
+/// [`OpenOptions::wait`] with `false` to return as soon as the dialog is shown. On Windows, a
+/// result of `Ok(())` only means the dialog was shown.
+///
+/// ## Platform Implementation Details
+///
+/// - On Windows, the "Open with" dialog is shown with the `openas` verb of the `ShellExecuteW`
+///   Windows API function.
+/// - On Linux, with the **portal** feature, the `OpenURI` or `OpenFile` method of the
+///   [`org.freedesktop.portal.OpenURI`] desktop portal is called with its `ask` option.
+/// - On Mac, and on Linux without the **portal** feature, [`OpenError::Unsupported`] is
+///   returned.
+///
+/// [`org.freedesktop.portal.OpenURI`]: https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.OpenURI.html
+pub fn open_with_chooser<P>(path: P) -> Result<(), OpenError>
+where
+    P: AsRef<OsStr>,
+{
+    OpenOptions::new().open_with_chooser(path).map(|_| ())
+}
+
+/// Lists the applications that can open a file or link, such as for an "Open With" menu, with the
+/// default application first.
+///
+/// ```no_run
+/// # fn main() -> Result<(), ::opener::OpenError> {
+/// let applications = opener::applications_for("notes.txt")?;
+/// for application in &applications {
+///     println!("{} ({})", application.name(), application.id());
+/// }
+/// if let Some(application) = applications.last() {
+///     application.open("notes.txt")?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ## Platform Implementation Details
+///
+/// - On Linux and other platforms, the MIME type of the path is determined from the
+///   shared-mime-info database, and the applications associated with it or one of its parent types
+///   are read from `mimeapps.list` and `mimeinfo.cache`, following the [XDG MIME Applications
+///   specification]. Removed associations and applications that aren't installed are left out.
+///   URLs are matched by their `x-scheme-handler/<scheme>` type.
+/// - On Windows and Mac, an empty list is currently returned.
+///
+/// [XDG MIME Applications specification]: https://specifications.freedesktop.org/mime-apps-spec/latest/
+pub fn applications_for<P>(path: P) -> Result<Vec<Application>, OpenError>
+where
+    P: AsRef<OsStr>,
+{
+    sys::applications_for(path.as_ref())
 }
 
 /// Opens a file or link with the system default program, using the `BROWSER` environment variable
@@ -88,31 +233,7 @@ pub fn open_browser<P>(path: P) -> Result<(), OpenError>
 where
     P: AsRef<OsStr>,
 {
-    let mut path = path.as_ref();
-    if let Ok(browser_var) = env::var("BROWSER") {
-        let windows_path;
-        if is_wsl() && browser_var.ends_with(".exe") {
-            if let Some(windows_path_2) = wsl_to_windows_path(path) {
-                windows_path = windows_path_2;
-                path = &windows_path;
-            }
-        };
-
-        Command::new(&browser_var)
-            .arg(path)
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(|err| OpenError::Spawn {
-                cmds: browser_var,
-                source: err,
-            })?;
-
-        Ok(())
-    } else {
-        sys::open(path)
-    }
+    OpenOptions::new().open_browser(path).map(|_| ())
 }
 
 /// Opens the default file explorer and reveals a file or folder in its containing folder.
@@ -134,7 +255,39 @@ pub fn reveal<P>(path: P) -> Result<(), OpenError>
 where
     P: AsRef<std::path::Path>,
 {
-    sys::reveal(path.as_ref())
+    OpenOptions::new().reveal(path)
+}
+
+/// Opens the default file explorer and reveals several files or folders at once, selecting them in
+/// their containing folders.
+///
+/// Does nothing if `paths` is empty.
+///
+/// ## Platform Implementation Details
+/// - On Windows, the items of each containing folder are selected with one
+///   `SHOpenFolderAndSelectItems` call.
+/// - On Windows Subsystem for Linux (WSL), `explorer.exe /select, <path>` is run for the first path
+///   in each containing folder, since it can only select one item.
+/// - On Mac the system `open -R` command is used, with all paths.
+/// - On non-WSL Linux all paths are passed to one `ShowItems` call of the
+///   [`file-manager-interface`]. If it's unavailable, the [`org.freedesktop.portal.OpenURI`] DBus
+///   Interface is used for the first path in each containing folder, falling back to opening each
+///   containing folder with [`open`].
+/// - On other platforms, each containing folder is shown with [`open`].
+///
+/// These are the [`Launcher`]s tried by default, after any custom [`Backend`]. They can be
+/// reordered, replaced or left out with [`OpenOptions::reveal_launchers`] and
+/// [`OpenOptions::disable`].
+///
+/// [`org.freedesktop.portal.OpenURI`]: https://flatpak.github.io/xdg-desktop-portal/#gdbus-org.freedesktop.portal.OpenURI
+/// [`file-manager-interface`]: https://freedesktop.org/wiki/Specifications/file-manager-interface/
+#[cfg(feature = "reveal")]
+pub fn reveal_many<I, P>(paths: I) -> Result<(), OpenError>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<std::path::Path>,
+{
+    OpenOptions::new().reveal_many(paths)
 }
 
 /// An error type representing the failure to open a path. Possibly returned by the [`open`]
@@ -157,7 +310,7 @@ pub enum OpenError {
     /// A command exited with a non-zero exit status.
     ExitStatus {
         /// A string that identifies the command.
-        cmd: &'static str,
+        cmd: String,
 
         /// The failed process's exit status.
         status: ExitStatus,
@@ -165,16 +318,92 @@ pub enum OpenError {
         /// Anything the process wrote to stderr.
         stderr: String,
     },
+
+    /// A launcher reported that it was given invalid arguments (`xdg-open` exit code 1).
+    InvalidArguments {
+        /// A string that identifies the command.
+        cmd: String,
+
+        /// Anything the process wrote to stderr.
+        stderr: String,
+    },
+
+    /// A launcher reported that the file to open does not exist (`xdg-open` exit code 2).
+    FileNotFound {
+        /// A string that identifies the command.
+        cmd: String,
+
+        /// Anything the process wrote to stderr.
+        stderr: String,
+    },
+
+    /// A launcher found no program to open the path with (`xdg-open` exit code 3).
+    NoHandler {
+        /// A string that identifies the command.
+        cmd: String,
+
+        /// Anything the process wrote to stderr.
+        stderr: String,
+    },
+
+    /// The program a launcher chose failed to open the path (`xdg-open` exit code 4).
+    ActionFailed {
+        /// A string that identifies the command.
+        cmd: String,
+
+        /// Anything the process wrote to stderr.
+        stderr: String,
+    },
+
+    /// No application is registered to open paths of a MIME type.
+    NoApplication {
+        /// The MIME type, such as `text/plain`, or `x-scheme-handler/https` for links.
+        mime_type: String,
+    },
+
+    /// The user cancelled a desktop portal request, such as by closing the application chooser
+    /// (portal response code 1).
+    Cancelled,
+
+    /// A desktop portal request failed in some other way, such as when the portal found no
+    /// application to open the path with (portal response code 2).
+    PortalFailed,
+
+    /// The operation isn't supported on this platform, with the enabled features, or in the
+    /// current environment, such as when no file manager implements the needed D-Bus interface.
+    Unsupported {
+        /// The operation, such as "showing file properties".
+        operation: &'static str,
+    },
+
+    /// Every backend that was tried failed, such as each launcher on Linux, or `FileManager1`, the
+    /// desktop portal and the containing folder fallback when revealing a path.
+    AllFailed {
+        /// The name of each backend that was tried, such as a [`Launcher`] or `FileManager1`, and
+        /// its error, in the order they were tried.
+        attempts: Vec<(String, OpenError)>,
+
+        /// Whether there was no graphical session to open the path in, as told by
+        /// [`Environment::is_headless`], which is likely why the launchers failed. Only set by
+        /// `open` on Linux and other platforms using `xdg-open`.
+        no_display: bool,
+    },
 }
 
 impl Display for OpenError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            OpenError::Io(_) => {
+            OpenError::Io(err) => {
                 write!(f, "IO error")?;
+                if f.alternate() {
+                    write!(f, ": {err}")?;
+                }
             }
-            OpenError::Spawn { cmds, source: _ } => {
+            OpenError::Spawn { cmds, source } => {
                 write!(f, "error spawning command(s) '{cmds}'")?;
+                if f.alternate() {
+                    write!(f, ": {source}")?;
+                }
             }
             OpenError::ExitStatus {
                 cmd,
@@ -182,11 +411,70 @@ impl Display for OpenError {
                 stderr,
             } => {
                 write!(f, "command '{cmd}' did not execute successfully; {status}")?;
-
-                let stderr = stderr.trim();
-                if !stderr.is_empty() {
-                    write!(f, "\ncommand stderr:\n{stderr}")?;
+                write_stderr(f, stderr)?;
+            }
+            OpenError::InvalidArguments { cmd, stderr } => {
+                write!(f, "command '{cmd}' was given invalid arguments")?;
+                write_stderr(f, stderr)?;
+            }
+            OpenError::FileNotFound { cmd, stderr } => {
+                write!(f, "command '{cmd}' reported that the file does not exist")?;
+                write_stderr(f, stderr)?;
+            }
+            OpenError::NoHandler { cmd, stderr } => {
+                write!(f, "command '{cmd}' found no program to open the path with")?;
+                write_stderr(f, stderr)?;
+            }
+            OpenError::ActionFailed { cmd, stderr } => {
+                write!(f, "command '{cmd}' failed to open the path")?;
+                write_stderr(f, stderr)?;
+            }
+            OpenError::NoApplication { mime_type } => {
+                write!(f, "no application is registered for '{mime_type}'")?;
+            }
+            OpenError::Cancelled => {
+                write!(f, "the request was cancelled")?;
+            }
+            OpenError::PortalFailed => {
+                write!(f, "the desktop portal failed to handle the request")?;
+            }
+            OpenError::Unsupported { operation } => {
+                write!(f, "{operation} isn't supported on this system")?;
+            }
+            OpenError::AllFailed {
+                attempts,
+                no_display,
+            } => {
+                write!(f, "all {} attempts failed", attempts.len())?;
+                if *no_display {
+                    write!(f, " without a graphical session")?;
                 }
+                for (backend, error) in attempts {
+                    let mut message = error.to_string();
+                    let mut source = error.source();
+                    while let Some(error) = source {
+                        // Some errors already include their source in their message.
+                        let error_message = error.to_string();
+                        if !message.contains(&error_message) {
+                            message = format!("{message}: {error_message}");
+                        }
+                        source = error.source();
+                    }
+                    // The alternate form includes the hint of each attempt.
+                    if f.alternate() {
+                        if let Some(hint) = error.hint() {
+                            message = format!("{message}\nhint: {hint}");
+                        }
+                    }
+                    write!(f, "\n- {backend}: {}", message.replace('\n', "\n  "))?;
+                }
+                return Ok(());
+            }
+        }
+
+        if f.alternate() {
+            if let Some(hint) = self.hint() {
+                write!(f, "\nhint: {hint}")?;
             }
         }
 
@@ -194,30 +482,360 @@ impl Display for OpenError {
     }
 }
 
+fn write_stderr(f: &mut Formatter<'_>, stderr: &str) -> fmt::Result {
+    let stderr = stderr.trim();
+    if !stderr.is_empty() {
+        write!(f, "\ncommand stderr:\n{stderr}")?;
+    }
+
+    Ok(())
+}
+
 impl Error for OpenError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
             OpenError::Io(inner) => Some(inner),
             OpenError::Spawn { cmds: _, source } => Some(source),
-            OpenError::ExitStatus { .. } => None,
+            OpenError::ExitStatus { .. }
+            | OpenError::InvalidArguments { .. }
+            | OpenError::FileNotFound { .. }
+            | OpenError::NoHandler { .. }
+            | OpenError::ActionFailed { .. }
+            | OpenError::NoApplication { .. }
+            | OpenError::Cancelled
+            | OpenError::PortalFailed
+            | OpenError::Unsupported { .. }
+            | OpenError::AllFailed { .. } => None,
         }
     }
 }
 
-#[cfg(target_os = "linux")]
-fn is_wsl() -> bool {
-    sys::is_wsl()
+/// Opens a folder in the file manager.
+///
+/// Unlike [`open`], which opens a folder with whichever application is the default for
+/// `inode/directory`, such as a code editor, this always uses the file manager.
+///
+/// ## Errors
+/// An error of kind [`io::ErrorKind::InvalidInput`] is returned if the path isn't a directory.
+/// If no file manager can be reached, the path isn't opened with [`open`] instead:
+/// [`OpenError::AllFailed`] lists why each method failed, and [`OpenError::Unsupported`] is
+/// returned where there's no method at all.
+///
+/// ## Platform Implementation Details
+/// - On Windows the `explore` verb of the `ShellExecuteW` Windows API function is used.
+/// - On Windows Subsystem for Linux (WSL) the `explorer.exe <path>` command is used.
+/// - On Mac the system `open -a Finder` command is used.
+/// - On non-WSL Linux the `ShowFolders` method of the [`file-manager-interface`] is used if
+///   available, then the [`org.freedesktop.portal.OpenURI`] DBus Interface, which shows the folder
+///   in its parent folder.
+/// - On other platforms, this isn't supported.
+///
+/// [`org.freedesktop.portal.OpenURI`]: https://flatpak.github.io/xdg-desktop-portal/#gdbus-org.freedesktop.portal.OpenURI
+/// [`file-manager-interface`]: https://freedesktop.org/wiki/Specifications/file-manager-interface/
+#[cfg(feature = "reveal")]
+pub fn open_folder<P>(path: P) -> Result<(), OpenError>
+where
+    P: AsRef<std::path::Path>,
+{
+    OpenOptions::new().open_folder(path)
 }
 
-#[cfg(not(target_os = "linux"))]
-fn is_wsl() -> bool {
-    false
+/// Shows the file manager's properties dialog for a file or folder.
+///
+/// ## Errors
+/// [`OpenError::Unsupported`] is returned if no properties dialog is available.
+///
+/// ## Platform Implementation Details
+/// - On Windows the `SHObjectProperties` Windows API function is used. The dialog belongs to the
+///   calling process, so it closes when the process exits.
+/// - On Mac, Finder's "Get Info" window is opened with `osascript`.
+/// - On non-WSL Linux the `ShowItemProperties` method of the [`file-manager-interface`] is used.
+/// - On Windows Subsystem for Linux (WSL) and other platforms, this isn't supported.
+///
+/// [`file-manager-interface`]: https://freedesktop.org/wiki/Specifications/file-manager-interface/
+#[cfg(feature = "reveal")]
+pub fn show_properties<P>(path: P) -> Result<(), OpenError>
+where
+    P: AsRef<std::path::Path>,
+{
+    OpenOptions::new().show_properties(path)
+}
+
+impl OpenError {
+    /// Combines the errors of the launchers that were tried: the error itself if only one was
+    /// tried, or [`OpenError::AllFailed`].
+    pub(crate) fn from_attempts(mut attempts: Vec<(String, OpenError)>) -> OpenError {
+        if attempts.len() > 1 {
+            return OpenError::AllFailed {
+                attempts,
+                no_display: false,
+            };
+        }
+        attempts.pop().map_or(
+            OpenError::Unsupported {
+                operation: "opening paths without any enabled launcher",
+            },
+            |(_, err)| err,
+        )
+    }
+
+    /// A suggestion for fixing the problem, such as which package to install, if there's one.
+    ///
+    /// The hint is also shown by the alternate form of `Display`, `{:#}`, which includes the
+    /// details of the underlying error as well:
+    ///
+    /// ```no_run
+    /// if let Err(err) = opener::open("report.pdf") {
+    ///     eprintln!("{err:#}");
+    /// }
+    /// ```
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            OpenError::Io(err) => io_hint(err),
+            OpenError::Spawn { cmds, source } if source.kind() == io::ErrorKind::NotFound => {
+                Some(match cmds.as_str() {
+                    "xdg-open" => "install xdg-utils, which provides xdg-open".to_owned(),
+                    "wslview" => "install wslu, which provides wslview".to_owned(),
+                    "sh" => "install a POSIX shell as sh, to run the embedded xdg-open script"
+                        .to_owned(),
+                    "explorer.exe" => {
+                        "enable Windows interoperability in WSL, and keep the Windows directories \
+                         in PATH"
+                            .to_owned()
+                    }
+                    cmds => format!("make sure '{cmds}' is installed and in PATH"),
+                })
+            }
+            OpenError::NoHandler { .. } => Some(
+                "install an application that can open this kind of file or link, or set a \
+                 default one"
+                    .to_owned(),
+            ),
+            OpenError::NoApplication { mime_type } => Some(format!(
+                "no application is registered for {mime_type}; set one with \
+                 `xdg-mime default <application>.desktop {mime_type}`"
+            )),
+            OpenError::AllFailed {
+                attempts,
+                no_display,
+            } => {
+                let mut hints: Vec<String> = Vec::new();
+                if *no_display {
+                    hints.push(
+                        "neither DISPLAY nor WAYLAND_DISPLAY is set; are you in an SSH session? \
+                         Run the program from a graphical session, or forward X11 with `ssh -X`"
+                            .to_owned(),
+                    );
+                }
+                for hint in attempts.iter().filter_map(|(_, error)| error.hint()) {
+                    if !hints.contains(&hint) {
+                        hints.push(hint);
+                    }
+                }
+                (!hints.is_empty()).then(|| hints.join("; "))
+            }
+            _ => None,
+        }
+    }
+
+    /// Classifies the error, so that it can be handled or explained without matching on every
+    /// variant and the [`io::ErrorKind`] of the errors they contain.
+    ///
+    /// For [`OpenError::AllFailed`], the most specific kind among the attempts is returned. For
+    /// example, if `xdg-open` isn't installed but the embedded `xdg-open` script reported that the
+    /// file doesn't exist, the kind is [`OpenErrorKind::NotFound`].
+    pub fn kind(&self) -> OpenErrorKind {
+        match self {
+            OpenError::Io(err) => io_error_kind(err).unwrap_or(match err.kind() {
+                io::ErrorKind::NotFound => OpenErrorKind::NotFound,
+                io::ErrorKind::PermissionDenied => OpenErrorKind::PermissionDenied,
+                io::ErrorKind::Unsupported => OpenErrorKind::Unsupported,
+                _ => OpenErrorKind::Other,
+            }),
+            OpenError::Spawn { cmds: _, source } => match source.kind() {
+                io::ErrorKind::NotFound => OpenErrorKind::LauncherMissing,
+                io::ErrorKind::PermissionDenied => OpenErrorKind::PermissionDenied,
+                _ => OpenErrorKind::Other,
+            },
+            OpenError::FileNotFound { .. } => OpenErrorKind::NotFound,
+            OpenError::NoHandler { .. } | OpenError::NoApplication { .. } => {
+                OpenErrorKind::NoHandler
+            }
+            OpenError::Cancelled => OpenErrorKind::Cancelled,
+            OpenError::Unsupported { .. } => OpenErrorKind::Unsupported,
+            OpenError::AllFailed {
+                attempts,
+                no_display,
+            } => {
+                let kinds: Vec<OpenErrorKind> =
+                    attempts.iter().map(|(_, error)| error.kind()).collect();
+                let kind = [
+                    OpenErrorKind::Cancelled,
+                    OpenErrorKind::NotFound,
+                    OpenErrorKind::PermissionDenied,
+                    OpenErrorKind::NoHandler,
+                    OpenErrorKind::NoDisplay,
+                    OpenErrorKind::Unsupported,
+                    OpenErrorKind::LauncherMissing,
+                ]
+                .into_iter()
+                .find(|kind| kinds.contains(kind))
+                .unwrap_or(OpenErrorKind::Other);
+                // Without a graphical session, `xdg-open` can only fall back to terminal programs,
+                // which explains why it found nothing to open the path with.
+                match kind {
+                    OpenErrorKind::NoHandler
+                    | OpenErrorKind::LauncherMissing
+                    | OpenErrorKind::Other
+                        if *no_display =>
+                    {
+                        OpenErrorKind::NoDisplay
+                    }
+                    kind => kind,
+                }
+            }
+            OpenError::ExitStatus { .. }
+            | OpenError::InvalidArguments { .. }
+            | OpenError::ActionFailed { .. }
+            | OpenError::PortalFailed => OpenErrorKind::Other,
+        }
+    }
+}
+
+/// A classification of [`OpenError`]s, returned by [`OpenError::kind`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpenErrorKind {
+    /// The file to open doesn't exist.
+    NotFound,
+
+    /// No application is registered to open the path.
+    NoHandler,
+
+    /// No launcher program, such as `xdg-open`, is installed.
+    LauncherMissing,
+
+    /// Access to the path, or to a launcher, was denied.
+    PermissionDenied,
+
+    /// The user cancelled the request, such as by closing an application chooser.
+    Cancelled,
+
+    /// There's no graphical session to open the path in.
+    NoDisplay,
+
+    /// The operation isn't supported on this platform or in this environment.
+    Unsupported,
+
+    /// Any other error.
+    Other,
+}
+
+/// The hint of an error created by [`classified_io_error`].
+fn io_hint(err: &io::Error) -> Option<String> {
+    #[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+    {
+        classified(err).and_then(|classified| classified.hint.map(str::to_owned))
+    }
+
+    #[cfg(not(all(any(feature = "reveal", feature = "portal"), target_os = "linux")))]
+    {
+        let _ = err;
+        None
+    }
+}
+
+/// The kind of an error created by [`classified_io_error`].
+fn io_error_kind(err: &io::Error) -> Option<OpenErrorKind> {
+    #[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+    {
+        classified(err).map(|classified| classified.kind)
+    }
+
+    #[cfg(not(all(any(feature = "reveal", feature = "portal"), target_os = "linux")))]
+    {
+        let _ = err;
+        None
+    }
+}
+
+#[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+fn classified(err: &io::Error) -> Option<&ClassifiedError> {
+    err.get_ref()
+        .and_then(|inner| inner.downcast_ref::<ClassifiedError>())
+}
+
+/// Wraps an error that has no [`io::ErrorKind`] of its own, such as a D-Bus error, in an
+/// [`io::Error`], along with its kind for [`OpenError::kind`] and a hint for [`OpenError::hint`].
+#[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+fn classified_io_error<E>(error: E, kind: OpenErrorKind, hint: Option<&'static str>) -> io::Error
+where
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    let io_kind = match kind {
+        OpenErrorKind::NotFound => io::ErrorKind::NotFound,
+        OpenErrorKind::PermissionDenied => io::ErrorKind::PermissionDenied,
+        OpenErrorKind::Unsupported => io::ErrorKind::Unsupported,
+        _ => io::ErrorKind::Other,
+    };
+    io::Error::new(
+        io_kind,
+        ClassifiedError {
+            error: error.into(),
+            kind,
+            hint,
+        },
+    )
+}
+
+#[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+#[derive(Debug)]
+struct ClassifiedError {
+    error: Box<dyn Error + Send + Sync>,
+    kind: OpenErrorKind,
+    hint: Option<&'static str>,
+}
+
+#[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+impl Display for ClassifiedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+#[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+impl Error for ClassifiedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Groups paths by their parent directory, keeping the order in which each directory first
+/// appears, for backends that can only select items in one folder at a time.
+#[cfg(all(feature = "reveal", not(target_os = "macos")))]
+fn group_by_parent<P>(paths: &[P]) -> Vec<Vec<&P>>
+where
+    P: AsRef<std::path::Path>,
+{
+    let mut groups: Vec<Vec<&P>> = Vec::new();
+    for path in paths {
+        let parent = path.as_ref().parent();
+        match groups
+            .iter_mut()
+            .find(|group| group[0].as_ref().parent() == parent)
+        {
+            Some(group) => group.push(path),
+            None => groups.push(vec![path]),
+        }
+    }
+    groups
 }
 
 #[cfg(target_os = "linux")]
 fn wsl_to_windows_path(path: &OsStr) -> Option<OsString> {
     use bstr::ByteSlice;
     use std::os::unix::ffi::OsStringExt;
+    use std::process::{Command, Stdio};
 
     let output = Command::new("wslpath")
         .arg("-w")
@@ -240,23 +858,102 @@ fn wsl_to_windows_path(_path: &OsStr) -> Option<OsString> {
     unreachable!()
 }
 
-#[cfg(not(target_os = "windows"))]
-fn wait_child(child: &mut std::process::Child, cmd_name: &'static str) -> Result<(), OpenError> {
-    use std::io::Read;
+/// Waits for a launcher process to exit and checks its exit status if `wait` is set, or otherwise
+/// leaves it to be reaped in the background.
+fn finish_child(mut child: Child, cmd_name: &str, wait: bool) -> Result<(), OpenError> {
+    if wait {
+        wait_child(&mut child, cmd_name)
+    } else {
+        reap_in_background(child);
+        Ok(())
+    }
+}
+
+/// Launched programs usually keep running after we return, and may inherit the launcher's stderr,
+/// so stderr is only captured when the launcher is waited for.
+fn stderr_for(wait: bool) -> Stdio {
+    if wait {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    }
+}
 
+/// Waits for a process on a background thread, so that it doesn't linger as a zombie process once
+/// it exits. The process keeps running if ours exits first.
+fn reap_in_background(mut child: Child) {
+    // Nothing reads from or writes to the pipes anymore. Keeping them open could block the process
+    // once it fills a pipe buffer.
+    drop(child.stdin.take());
+    drop(child.stderr.take());
+
+    // Waiting doesn't need much stack, and a thread may be around for as long as the launched
+    // program is (some `xdg-open` implementations don't exit until it does).
+    const REAPER_STACK_SIZE: usize = 64 * 1024;
+
+    // If the thread can't be spawned, the child is dropped without being waited for, just as it
+    // would have been without a reaper.
+    let _ = thread::Builder::new()
+        .name("opener-reaper".into())
+        .stack_size(REAPER_STACK_SIZE)
+        .spawn(move || {
+            let _ = child.wait();
+        });
+}
+
+fn wait_child(child: &mut Child, cmd_name: &str) -> Result<(), OpenError> {
+    let stderr = child.stderr.take().map(read_in_background);
     let exit_status = child.wait().map_err(OpenError::Io)?;
     if exit_status.success() {
         Ok(())
     } else {
-        let mut stderr_output = String::new();
-        if let Some(stderr) = child.stderr.as_mut() {
-            stderr.read_to_string(&mut stderr_output).ok();
-        }
-
         Err(OpenError::ExitStatus {
-            cmd: cmd_name,
+            cmd: cmd_name.to_owned(),
             status: exit_status,
-            stderr: stderr_output,
+            stderr: stderr.map(collect_output).unwrap_or_default(),
         })
     }
 }
+
+/// Reads from a pipe on a background thread.
+///
+/// A launched program may inherit its launcher's stderr, so reading a launcher's stderr to the end
+/// could block for as long as the launched program runs.
+fn read_in_background<R>(mut pipe: R) -> mpsc::Receiver<Vec<u8>>
+where
+    R: Read + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let _ = thread::Builder::new()
+        .name("opener-stderr".into())
+        .spawn(move || {
+            let mut buf = [0; 4096];
+            while let Ok(n @ 1..) = pipe.read(&mut buf) {
+                if sender.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+    receiver
+}
+
+/// Collects the output read so far by [`read_in_background`], giving it a moment to catch up with
+/// output written just before the process exited.
+///
+/// The moment is bounded as a whole, since a launched program that inherited the pipe may keep
+/// writing to it.
+fn collect_output(receiver: mpsc::Receiver<Vec<u8>>) -> String {
+    const GRACE_PERIOD: Duration = Duration::from_millis(100);
+
+    let deadline = Instant::now() + GRACE_PERIOD;
+    let mut output = Vec::new();
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(remaining) {
+            Ok(chunk) => output.extend(chunk),
+            Err(_) => break,
+        }
+    }
+
+    String::from_utf8_lossy(&output).into_owned()
+}
@@ -1,151 +1,388 @@
-use crate::OpenError;
+pub use self::desktop_entry::{DesktopAction, DesktopEntry};
+pub use self::desktop_environment::{desktop_environment, DesktopEnvironment};
+pub use self::mime::mime_type;
+pub use self::mimeapps::set_default_application;
+use self::mimeapps::MimeApps;
+pub use self::scheme_handler::UrlSchemeHandler;
+use crate::{Application, Launched, Launcher, OpenError, OpenOptions};
 use std::ffi::OsStr;
-use std::io;
 use std::io::Write;
 use std::process::{Child, Command, Stdio};
 
+mod desktop_entry;
+mod desktop_environment;
+mod key_file;
+mod mime;
+mod mimeapps;
+#[cfg(feature = "native-xdg")]
+mod native;
+mod scheme_handler;
+mod xdg_dirs;
+
 const XDG_OPEN_SCRIPT: &[u8] = include_bytes!("xdg-open");
 
-pub(crate) fn open(path: &OsStr) -> Result<(), OpenError> {
-    if crate::is_wsl() {
-        wsl_open(path)
+pub(crate) fn default_launchers(options: &OpenOptions) -> Vec<Launcher> {
+    let environment = options.runtime_environment();
+    let mut defaults = Vec::new();
+    // Inside a sandbox, only the portal can open paths with applications from outside of it, and
+    // only the portal can ask which application to use.
+    if (environment.is_sandboxed() || options.should_ask()) && Launcher::Portal.is_supported() {
+        defaults.push(Launcher::Portal);
+    }
+    if environment.is_wsl() {
+        defaults.extend([Launcher::WslView, Launcher::XdgOpen]);
     } else {
-        non_wsl_open(path)
+        if cfg!(feature = "native-xdg") {
+            defaults.push(Launcher::Native);
+        }
+        defaults.extend([Launcher::XdgOpen, Launcher::EmbeddedXdgOpen]);
     }
+    defaults
 }
 
-#[cfg(all(feature = "reveal", target_os = "linux"))]
-pub(crate) fn reveal(path: &std::path::Path) -> Result<(), OpenError> {
-    if crate::is_wsl() {
-        reveal_in_windows_explorer(path)
-    } else {
-        crate::freedesktop::reveal_with_dbus(path).or_else(|_| reveal_fallback(path))
+/// Whether a launcher can be used. Programs are looked up in the `PATH` they'd be spawned with,
+/// which may be set with [`OpenOptions::env`].
+pub(crate) fn is_launcher_available(launcher: &Launcher, options: &OpenOptions) -> bool {
+    let program = match launcher {
+        Launcher::XdgOpen => "xdg-open",
+        Launcher::EmbeddedXdgOpen => "sh",
+        Launcher::WslView => "wslview",
+        launcher => return launcher.is_supported(),
+    };
+    desktop_entry::find_program_in(OsStr::new(program), options.path_var().as_deref()).is_some()
+}
+
+pub(crate) fn open(path: &OsStr, options: &OpenOptions) -> Result<Launched, OpenError> {
+    let environment = options.runtime_environment();
+    options
+        .try_launchers(path, &default_launchers(options), |launcher| {
+            open_with_launcher(path, launcher, options)
+        })
+        .map_err(|err| match err {
+            // Windows programs don't need a graphical session under WSL.
+            OpenError::AllFailed { attempts, .. } => OpenError::AllFailed {
+                attempts,
+                no_display: environment.is_headless() && !environment.is_wsl(),
+            },
+            err => err,
+        })
+}
+
+fn open_with_launcher(
+    path: &OsStr,
+    launcher: &Launcher,
+    options: &OpenOptions,
+) -> Result<Launched, OpenError> {
+    #[cfg(all(feature = "portal", target_os = "linux"))]
+    if *launcher == Launcher::Portal {
+        // When waited for, the portal responds once the request has been handled, and asking for
+        // an application is always waited for by default.
+        let ask = options.should_ask();
+        let wait = options.should_wait(ask);
+        crate::freedesktop::open_with_portal(path, ask, wait, options)?;
+        return Ok(Launched::new(Launcher::Portal, None, wait));
+    }
+
+    // wslview exits as soon as it has handed the path over to Windows, so it's always safe to
+    // wait for.
+    let wait = options.should_wait(*launcher == Launcher::WslView);
+    let (cmd_name, child): (String, _) = match launcher {
+        Launcher::WslView => ("wslview".into(), open_with_wslview(path, wait, options)?),
+        Launcher::XdgOpen => (
+            "xdg-open".into(),
+            open_with_system_xdg_open(path, wait, options)?,
+        ),
+        Launcher::EmbeddedXdgOpen => (
+            "sh".into(),
+            open_with_internal_xdg_open(path, wait, options)?,
+        ),
+        #[cfg(feature = "native-xdg")]
+        Launcher::Native => native::open(path, wait, options)?,
+        _ => {
+            return Err(OpenError::Unsupported {
+                operation: "opening paths with this launcher",
+            })
+        }
+    };
+
+    let pid = child.id();
+    match crate::finish_child(child, &cmd_name, wait) {
+        Ok(()) => Ok(Launched::new(launcher.clone(), Some(pid), wait)),
+        Err(err) if matches!(launcher, Launcher::XdgOpen | Launcher::EmbeddedXdgOpen) => {
+            Err(xdg_open_error(err))
+        }
+        Err(err) => Err(err),
     }
 }
 
-#[cfg(all(feature = "reveal", not(target_os = "linux")))]
-pub(crate) fn reveal(path: &std::path::Path) -> Result<(), OpenError> {
-    reveal_fallback(path)
+pub(crate) fn open_with_chooser(
+    path: &OsStr,
+    options: &OpenOptions,
+) -> Result<Launched, OpenError> {
+    #[cfg(all(feature = "portal", target_os = "linux"))]
+    {
+        let wait = options.should_wait(true);
+        crate::freedesktop::open_with_portal(path, true, wait, options)?;
+        Ok(Launched::new(Launcher::Portal, None, wait))
+    }
+
+    #[cfg(not(all(feature = "portal", target_os = "linux")))]
+    {
+        let _ = (path, options);
+        Err(OpenError::Unsupported {
+            operation: "choosing an application",
+        })
+    }
 }
 
-#[cfg(feature = "reveal")]
-fn reveal_fallback(path: &std::path::Path) -> Result<(), OpenError> {
-    let path = path.canonicalize().map_err(OpenError::Io)?;
-    let parent = path.parent().unwrap_or(std::path::Path::new("/"));
-    open(parent.as_os_str())
+/// Maps the [documented exit codes] of `xdg-open` to more specific errors.
+///
+/// [documented exit codes]: https://portland.freedesktop.org/doc/xdg-open.html#exit_codes
+fn xdg_open_error(err: OpenError) -> OpenError {
+    match err {
+        OpenError::ExitStatus {
+            cmd,
+            status,
+            stderr,
+        } => match status.code() {
+            Some(1) => OpenError::InvalidArguments { cmd, stderr },
+            Some(2) => OpenError::FileNotFound { cmd, stderr },
+            Some(3) => OpenError::NoHandler { cmd, stderr },
+            Some(4) => OpenError::ActionFailed { cmd, stderr },
+            _ => OpenError::ExitStatus {
+                cmd,
+                status,
+                stderr,
+            },
+        },
+        err => err,
+    }
 }
 
-fn wsl_open(path: &OsStr) -> Result<(), OpenError> {
-    let result = open_with_wslview(path);
-    if let Ok(mut child) = result {
-        return crate::wait_child(&mut child, "wslview");
+pub(crate) fn open_with(
+    path: &OsStr,
+    app: &OsStr,
+    options: &OpenOptions,
+) -> Result<Launched, OpenError> {
+    let entry = app.to_str().and_then(DesktopEntry::find);
+    let (launcher, mut cmd) = match &entry {
+        Some(entry) => (
+            Launcher::DesktopEntry,
+            entry.command(path).map_err(OpenError::Io)?,
+        ),
+        None => {
+            let mut cmd = Command::new(app);
+            cmd.arg(path);
+            (Launcher::Executable, cmd)
+        }
+    };
+
+    let wait = options.should_wait(false);
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(crate::stderr_for(wait));
+
+    let cmd_name = cmd.get_program().to_string_lossy().into_owned();
+    let child = spawn(cmd, &cmd_name, options)?;
+    let pid = child.id();
+    crate::finish_child(child, &cmd_name, wait)?;
+
+    Ok(Launched::new(launcher, Some(pid), wait))
+}
+
+pub(crate) fn applications_for(path: &OsStr) -> Result<Vec<Application>, OpenError> {
+    let (mime_types, _) = mime::target_mime_types(path, None).map_err(OpenError::Io)?;
+    let applications = MimeApps::load()
+        .applications(&mime_types)
+        .into_iter()
+        .filter_map(|entry| {
+            let id = entry.id()?.to_owned();
+            let name = entry
+                .name()
+                .unwrap_or_else(|| id.trim_end_matches(".desktop").to_owned());
+            Some(Application::new(id, name, entry.icon()))
+        })
+        .collect();
+
+    Ok(applications)
+}
+
+/// On Linux, `org.freedesktop.FileManager1` is tried before the desktop portal. The portal
+/// correctly handles runtimes such as Flatpak, but its original version didn't select the
+/// revealed items, and it's still in use by some distributions.
+/// Source: https://chromium-review.googlesource.com/c/chromium/src/+/3009959
+#[cfg(feature = "reveal")]
+pub(crate) fn default_reveal_launchers(options: &OpenOptions) -> Vec<Launcher> {
+    if !cfg!(target_os = "linux") {
+        vec![Launcher::ContainingFolder]
+    } else if options.runtime_environment().is_wsl() {
+        vec![Launcher::Explorer]
+    } else {
+        vec![
+            Launcher::FileManager1,
+            Launcher::Portal,
+            Launcher::ContainingFolder,
+        ]
     }
+}
 
-    open_with_system_xdg_open(path).map_err(|err| OpenError::Spawn {
-        cmds: "wslview, xdg-open".into(),
-        source: err,
-    })?;
+#[cfg(feature = "reveal")]
+pub(crate) fn reveal_with_launcher(
+    launcher: &Launcher,
+    paths: &[&std::path::Path],
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    match launcher {
+        #[cfg(target_os = "linux")]
+        Launcher::FileManager1 => crate::freedesktop::reveal_with_filemanager1(paths, options),
+        #[cfg(target_os = "linux")]
+        Launcher::Portal => crate::freedesktop::reveal_with_open_uri_portal(paths, options),
+        // explorer.exe can only select one item.
+        #[cfg(target_os = "linux")]
+        Launcher::Explorer => crate::group_by_parent(paths)
+            .into_iter()
+            .try_for_each(|group| run_windows_explorer(group[0], true, options)),
+        Launcher::ContainingFolder => reveal_fallback(paths, options),
+        _ => Err(OpenError::Unsupported {
+            operation: "revealing paths with this launcher",
+        }),
+    }
+}
 
-    Ok(())
+/// The default application for directories isn't necessarily a file manager, so there's no
+/// falling back to it.
+#[cfg(feature = "reveal")]
+pub(crate) fn open_folder_with_launcher(
+    launcher: &Launcher,
+    path: &std::path::Path,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    #[cfg(not(target_os = "linux"))]
+    let _ = (path, options);
+    match launcher {
+        #[cfg(target_os = "linux")]
+        Launcher::FileManager1 => crate::freedesktop::show_folder_with_filemanager1(path, options),
+        #[cfg(target_os = "linux")]
+        Launcher::Portal => crate::freedesktop::reveal_with_open_uri_portal(&[path], options),
+        #[cfg(target_os = "linux")]
+        Launcher::Explorer => run_windows_explorer(path, false, options),
+        _ => Err(OpenError::Unsupported {
+            operation: "opening folders with this launcher",
+        }),
+    }
 }
 
-fn non_wsl_open(path: &OsStr) -> Result<(), OpenError> {
-    if open_with_system_xdg_open(path).is_err() {
-        open_with_internal_xdg_open(path)?;
+#[cfg(all(feature = "reveal", target_os = "linux"))]
+pub(crate) fn show_properties(
+    path: &std::path::Path,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    if options.runtime_environment().is_wsl() {
+        Err(OpenError::Unsupported {
+            operation: "showing file properties",
+        })
+    } else {
+        crate::freedesktop::show_properties_with_dbus(path, options)
     }
+}
 
-    Ok(())
+#[cfg(all(feature = "reveal", not(target_os = "linux")))]
+pub(crate) fn show_properties(
+    _path: &std::path::Path,
+    _options: &OpenOptions,
+) -> Result<(), OpenError> {
+    Err(OpenError::Unsupported {
+        operation: "showing file properties",
+    })
 }
 
-fn open_with_wslview(path: &OsStr) -> io::Result<Child> {
-    Command::new("wslview")
-        .arg(path)
+#[cfg(feature = "reveal")]
+fn reveal_fallback(paths: &[&std::path::Path], options: &OpenOptions) -> Result<(), OpenError> {
+    let paths = paths
+        .iter()
+        .map(|path| path.canonicalize())
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(OpenError::Io)?;
+    crate::group_by_parent(&paths)
+        .into_iter()
+        .try_for_each(|group| {
+            let parent = group[0].parent().unwrap_or(std::path::Path::new("/"));
+            open(parent.as_os_str(), options).map(|_| ())
+        })
+}
+
+fn open_with_wslview(path: &OsStr, wait: bool, options: &OpenOptions) -> Result<Child, OpenError> {
+    let mut cmd = Command::new("wslview");
+    cmd.arg(path)
         .stdin(Stdio::null())
         .stdout(Stdio::null())
-        .stderr(Stdio::piped())
-        .spawn()
+        .stderr(crate::stderr_for(wait));
+    spawn(cmd, "wslview", options)
 }
 
-fn open_with_system_xdg_open(path: &OsStr) -> io::Result<Child> {
-    Command::new("xdg-open")
-        .arg(path)
+fn open_with_system_xdg_open(
+    path: &OsStr,
+    wait: bool,
+    options: &OpenOptions,
+) -> Result<Child, OpenError> {
+    let mut cmd = Command::new("xdg-open");
+    cmd.arg(path)
         .stdin(Stdio::null())
         .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
+        .stderr(crate::stderr_for(wait));
+    spawn(cmd, "xdg-open", options)
 }
 
-fn open_with_internal_xdg_open(path: &OsStr) -> Result<Child, OpenError> {
-    let mut sh = Command::new("sh")
-        .arg("-s")
+fn open_with_internal_xdg_open(
+    path: &OsStr,
+    wait: bool,
+    options: &OpenOptions,
+) -> Result<Child, OpenError> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-s")
         .arg(path)
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .map_err(|err| OpenError::Spawn {
-            cmds: "sh".into(),
-            source: err,
-        })?;
-
-    sh.stdin
-        .as_mut()
-        .unwrap()
-        .write_all(XDG_OPEN_SCRIPT)
-        .map_err(OpenError::Io)?;
+        .stderr(crate::stderr_for(wait));
+    let mut sh = spawn(cmd, "sh", options)?;
+
+    if let Err(err) = sh.stdin.as_mut().unwrap().write_all(XDG_OPEN_SCRIPT) {
+        // A partially written script mustn't run, and the shell still needs to be waited for.
+        let _ = sh.kill();
+        let _ = sh.wait();
+        return Err(OpenError::Io(err));
+    }
 
     Ok(sh)
 }
 
+fn spawn(mut cmd: Command, cmd_name: &str, options: &OpenOptions) -> Result<Child, OpenError> {
+    options.configure(&mut cmd);
+    cmd.spawn().map_err(|err| OpenError::Spawn {
+        cmds: cmd_name.into(),
+        source: err,
+    })
+}
+
+/// Runs `explorer.exe` with a path, either selecting it in its folder or opening it.
 #[cfg(all(feature = "reveal", target_os = "linux"))]
-fn reveal_in_windows_explorer(path: &std::path::Path) -> Result<(), OpenError> {
+fn run_windows_explorer(
+    path: &std::path::Path,
+    select: bool,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
     let converted_path = crate::wsl_to_windows_path(path.as_os_str());
     let converted_path = converted_path.as_deref();
     let path = match converted_path {
         None => path,
         Some(x) => std::path::Path::new(x),
     };
-    Command::new("explorer.exe")
-        .arg("/select,")
-        .arg(path)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .map_err(|err| OpenError::Spawn {
-            cmds: "explorer.exe".into(),
-            source: err,
-        })?;
-    Ok(())
-}
-
-#[cfg(target_os = "linux")]
-pub(crate) fn is_wsl() -> bool {
-    if is_docker() {
-        return false;
-    }
-
-    if let Ok(true) = std::fs::read_to_string("/proc/sys/kernel/osrelease")
-        .map(|osrelease| osrelease.to_ascii_lowercase().contains("microsoft"))
-    {
-        return true;
+    let mut cmd = Command::new("explorer.exe");
+    if select {
+        cmd.arg("/select,");
     }
-
-    if let Ok(true) = std::fs::read_to_string("/proc/version")
-        .map(|version| version.to_ascii_lowercase().contains("microsoft"))
-    {
-        return true;
-    }
-
-    false
-}
-
-#[cfg(target_os = "linux")]
-fn is_docker() -> bool {
-    let has_docker_env = std::fs::metadata("/.dockerenv").is_ok();
-
-    let has_docker_cgroup = std::fs::read_to_string("/proc/self/cgroup")
-        .map(|cgroup| cgroup.to_ascii_lowercase().contains("docker"))
-        .unwrap_or(false);
-
-    has_docker_env || has_docker_cgroup
+    cmd.arg(path).stdout(Stdio::null()).stderr(Stdio::null());
+    let explorer = spawn(cmd, "explorer.exe", options)?;
+    // explorer.exe's exit status is meaningless (it's 1 even on success), so it's never waited on.
+    crate::finish_child(explorer, "explorer.exe", false)
 }
@@ -0,0 +1,645 @@
+//! Parsing and launching of `.desktop` files, as described by the [Desktop Entry Specification].
+//!
+//! [Desktop Entry Specification]: https://specifications.freedesktop.org/desktop-entry-spec/latest/
+
+use super::key_file::{split_list, KeyFile};
+use super::xdg_dirs;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs, io};
+
+const GROUP: &str = "Desktop Entry";
+
+/// Terminal emulators used to run entries with `Terminal=true`, with the arguments that precede the
+/// command to run, in order of preference.
+const TERMINALS: &[(&str, &[&str])] = &[
+    ("xdg-terminal-exec", &[]),
+    ("x-terminal-emulator", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("xterm", &["-e"]),
+];
+
+/// A parsed [desktop entry]: the `.desktop` file that describes how to launch an application.
+///
+/// Only the `[Desktop Entry]` group is exposed directly; `[Desktop Action <id>]` groups are
+/// available through [`actions`](DesktopEntry::actions).
+///
+/// ```no_run
+/// # fn main() -> std::io::Result<()> {
+/// use opener::DesktopEntry;
+///
+/// if let Some(entry) = DesktopEntry::find("org.gnome.TextEditor") {
+///     println!("{:?} opens {:?}", entry.name(), entry.mime_types());
+///
+///     // One command line per instance to launch.
+///     for argv in entry.expand_exec(["notes.txt", "todo.txt"])? {
+///         println!("{argv:?}");
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [desktop entry]: https://specifications.freedesktop.org/desktop-entry-spec/latest/
+#[derive(Debug, Clone)]
+pub struct DesktopEntry {
+    id: Option<String>,
+    path: Option<PathBuf>,
+    key_file: KeyFile,
+}
+
+impl DesktopEntry {
+    /// Finds the desktop entry with the given [desktop file ID], such as `org.gimp.GIMP.desktop`,
+    /// in the `applications` subdirectory of the XDG data directories. The `.desktop` suffix is
+    /// optional.
+    ///
+    /// An entry with `Hidden=true` counts as deleted, so `None` is returned for it.
+    ///
+    /// [desktop file ID]: https://specifications.freedesktop.org/desktop-entry-spec/latest/file-naming.html#desktop-file-id
+    pub fn find(id: &str) -> Option<DesktopEntry> {
+        let id = normalize_id(id).ok()?;
+        let path = xdg_dirs::all_data_dirs()
+            .into_iter()
+            .find_map(|dir| find_in_dir(&dir.join("applications"), "", &id))?;
+        let mut entry = DesktopEntry::from_path(path).ok()?;
+        if entry.is_hidden() {
+            return None;
+        }
+        entry.id = Some(id);
+        Some(entry)
+    }
+
+    /// Reads and parses a `.desktop` file.
+    pub fn from_path<P>(path: P) -> io::Result<DesktopEntry>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut entry = DesktopEntry::parse(&fs::read_to_string(path)?);
+        entry.path = Some(path.to_owned());
+        Ok(entry)
+    }
+
+    /// Parses the contents of a `.desktop` file. Invalid lines are ignored.
+    pub fn parse(contents: &str) -> DesktopEntry {
+        DesktopEntry {
+            id: None,
+            path: None,
+            key_file: KeyFile::parse(contents),
+        }
+    }
+
+    /// The desktop file ID, if the entry was looked up with [`find`](DesktopEntry::find).
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// The path the entry was read from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Gets the value of a key, with escape sequences such as `\s` replaced.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.key_file.get(GROUP, key).map(unescape)
+    }
+
+    /// Gets the value of a localized key, such as `Name[de]`, for the given locale.
+    ///
+    /// The locale has the form `lang_COUNTRY.ENCODING@MODIFIER`, where every part but `lang` is
+    /// optional. If no locale is given, the current one is taken from `$LC_ALL`, `$LC_MESSAGES` or
+    /// `$LANG`. The best match is picked as described by the specification, falling back to the
+    /// unlocalized key.
+    pub fn get_localized(&self, key: &str, locale: Option<&str>) -> Option<String> {
+        localized(&self.key_file, GROUP, key, locale)
+    }
+
+    /// The localized `Name` of the application.
+    pub fn name(&self) -> Option<String> {
+        self.get_localized("Name", None)
+    }
+
+    /// The localized `Icon` key: an icon name or an absolute path.
+    pub fn icon(&self) -> Option<String> {
+        self.get_localized("Icon", None)
+    }
+
+    /// The `Exec` key: the command line to launch, with field codes not yet expanded.
+    pub fn exec(&self) -> Option<String> {
+        self.get("Exec")
+    }
+
+    /// The `TryExec` key: an executable used to determine whether the application is installed.
+    pub fn try_exec(&self) -> Option<String> {
+        self.get("TryExec")
+    }
+
+    /// Whether the application runs in a terminal, as indicated by the `Terminal` key.
+    pub fn terminal(&self) -> bool {
+        self.get_bool("Terminal")
+    }
+
+    /// Whether the entry should be treated as deleted, as indicated by the `Hidden` key.
+    pub fn is_hidden(&self) -> bool {
+        self.get_bool("Hidden")
+    }
+
+    /// Whether the entry should be left out of menus, as indicated by the `NoDisplay` key. Such
+    /// applications can still be used to open files.
+    pub fn no_display(&self) -> bool {
+        self.get_bool("NoDisplay")
+    }
+
+    /// Whether the application is installed: `true` unless the `TryExec` key names a program that
+    /// isn't an executable file, either as a path or in `$PATH`.
+    pub fn is_installed(&self) -> bool {
+        match self.try_exec() {
+            Some(program) => find_program(OsStr::new(&program)).is_some(),
+            None => true,
+        }
+    }
+
+    /// The MIME types the application can open, from the `MimeType` key.
+    pub fn mime_types(&self) -> Vec<String> {
+        self.get("MimeType")
+            .map(|value| split_list(&value).map(str::to_owned).collect())
+            .unwrap_or_default()
+    }
+
+    /// The additional actions listed in the `Actions` key, such as "New Window". Actions without a
+    /// `[Desktop Action <id>]` group are skipped.
+    pub fn actions(&self) -> Vec<DesktopAction> {
+        let Some(ids) = self.get("Actions") else {
+            return Vec::new();
+        };
+
+        split_list(&ids)
+            .filter_map(|id| {
+                let group = format!("Desktop Action {id}");
+                self.key_file.group(&group).next()?;
+                Some(DesktopAction {
+                    id: id.to_owned(),
+                    name: localized(&self.key_file, &group, "Name", None),
+                    icon: localized(&self.key_file, &group, "Icon", None),
+                    exec: self.key_file.get(&group, "Exec").map(unescape),
+                    entry: self.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Expands the `Exec` key into the command lines that launch the application with `targets`,
+    /// which are file paths or URLs.
+    ///
+    /// Field codes are replaced as described by the specification: `%f` and `%u` by a single
+    /// target, `%F` and `%U` by all targets as separate arguments, `%i` by `--icon` and the icon,
+    /// `%c` by the localized name, `%k` by the path of the `.desktop` file and `%%` by `%`.
+    /// Deprecated field codes are removed. If the command line has `%f` or `%u` but no `%F` or
+    /// `%U`, one command line is returned per target. If it has no file or URL field code at all,
+    /// the targets are appended, as `xdg-open` does.
+    ///
+    /// Returns an error if there's no `Exec` key, or if its quoting is invalid.
+    pub fn expand_exec<I, S>(&self, targets: I) -> io::Result<Vec<Vec<OsString>>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let exec = self.exec().ok_or_else(|| self.invalid("has no Exec key"))?;
+        expand_exec(
+            &exec,
+            &collect_targets(targets),
+            &self.exec_context(self.icon()),
+        )
+        .ok_or_else(|| self.invalid("has unbalanced quotes in its Exec key"))
+    }
+
+    /// Builds the command that opens `target` with the application, run in a terminal emulator if
+    /// the entry asks for one.
+    pub(crate) fn command(&self, target: &OsStr) -> io::Result<Command> {
+        let argv = self
+            .expand_exec([target])?
+            .into_iter()
+            .next()
+            .filter(|argv| !argv.is_empty())
+            .ok_or_else(|| self.invalid("has an empty Exec key"))?;
+
+        Ok(command_for(argv, self.terminal()))
+    }
+
+    fn get_bool(&self, key: &str) -> bool {
+        self.key_file.get(GROUP, key) == Some("true")
+    }
+
+    fn exec_context(&self, icon: Option<String>) -> ExecContext {
+        ExecContext {
+            name: self.name(),
+            icon,
+            location: self.path.clone(),
+        }
+    }
+
+    fn invalid(&self, problem: &str) -> io::Error {
+        let entry = match (&self.id, &self.path) {
+            (Some(id), _) => id.clone(),
+            (None, Some(path)) => path.display().to_string(),
+            (None, None) => "<unnamed>".to_owned(),
+        };
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("desktop entry '{entry}' {problem}"),
+        )
+    }
+}
+
+/// An additional action of an application, such as "New Window", from a `[Desktop Action <id>]`
+/// group of its [`DesktopEntry`].
+#[derive(Debug, Clone)]
+pub struct DesktopAction {
+    id: String,
+    name: Option<String>,
+    icon: Option<String>,
+    exec: Option<String>,
+    entry: DesktopEntry,
+}
+
+impl DesktopAction {
+    /// The action identifier, as listed in the `Actions` key.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The localized `Name` of the action.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The localized `Icon` of the action.
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    /// The `Exec` key of the action, with field codes not yet expanded.
+    pub fn exec(&self) -> Option<&str> {
+        self.exec.as_deref()
+    }
+
+    /// Expands the `Exec` key of the action. See [`DesktopEntry::expand_exec`]; `%i` falls back to
+    /// the icon of the application.
+    pub fn expand_exec<I, S>(&self, targets: I) -> io::Result<Vec<Vec<OsString>>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        let invalid = |problem: &str| {
+            self.entry
+                .invalid(&format!("action '{}' {problem}", self.id))
+        };
+
+        let exec = self
+            .exec
+            .as_deref()
+            .ok_or_else(|| invalid("has no Exec key"))?;
+        let icon = self.icon.clone().or_else(|| self.entry.icon());
+        expand_exec(
+            exec,
+            &collect_targets(targets),
+            &self.entry.exec_context(icon),
+        )
+        .ok_or_else(|| invalid("has unbalanced quotes in its Exec key"))
+    }
+}
+
+/// Checks that `id` can be a desktop file ID, and adds the `.desktop` suffix if it's missing.
+pub(crate) fn normalize_id(id: &str) -> io::Result<String> {
+    if id.is_empty() || id.contains(['/', ';', '\n', '\r']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid desktop file ID '{id}'"),
+        ));
+    }
+
+    Ok(if id.ends_with(".desktop") {
+        id.to_owned()
+    } else {
+        format!("{id}.desktop")
+    })
+}
+
+/// The values of the field codes other than the file and URL ones.
+struct ExecContext {
+    name: Option<String>,
+    icon: Option<String>,
+    location: Option<PathBuf>,
+}
+
+/// A piece of an `Exec` argument.
+enum Part {
+    Text(String),
+    FieldCode(char),
+}
+
+fn collect_targets<I, S>(targets: I) -> Vec<OsString>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    targets
+        .into_iter()
+        .map(|target| target.as_ref().to_owned())
+        .collect()
+}
+
+/// Expands an unescaped `Exec` value into command lines. Returns `None` if its quoting is invalid.
+fn expand_exec(
+    exec: &str,
+    targets: &[OsString],
+    context: &ExecContext,
+) -> Option<Vec<Vec<OsString>>> {
+    let args = split_exec(exec)?;
+    let has_field_code = |codes: &[char]| {
+        args.iter()
+            .flatten()
+            .any(|part| matches!(part, Part::FieldCode(c) if codes.contains(c)))
+    };
+
+    // `%F` and `%U` only expand to several arguments when they are arguments on their own.
+    let has_list = args.iter().any(|arg| is_list_arg(arg));
+    if !has_list && !has_field_code(&['f', 'u', 'F', 'U']) {
+        let mut argv = expand_args(&args, &[], context);
+        argv.extend(targets.iter().cloned());
+        return Some(vec![argv]);
+    }
+
+    if !has_list && targets.len() > 1 {
+        Some(
+            targets
+                .iter()
+                .map(|target| expand_args(&args, std::slice::from_ref(target), context))
+                .collect(),
+        )
+    } else {
+        Some(vec![expand_args(&args, targets, context)])
+    }
+}
+
+fn is_list_arg(arg: &[Part]) -> bool {
+    matches!(arg[..], [Part::FieldCode('F' | 'U')])
+}
+
+fn expand_args(args: &[Vec<Part>], targets: &[OsString], context: &ExecContext) -> Vec<OsString> {
+    let mut argv = Vec::new();
+    for arg in args {
+        if is_list_arg(arg) {
+            argv.extend(targets.iter().cloned());
+            continue;
+        }
+        if let [Part::FieldCode('i')] = arg[..] {
+            if let Some(icon) = &context.icon {
+                argv.push("--icon".into());
+                argv.push(icon.into());
+            }
+            continue;
+        }
+
+        // Arguments made up only of field codes that expand to nothing are dropped.
+        let mut expanded = OsString::new();
+        let mut keep = false;
+        for part in arg {
+            let value = match part {
+                Part::Text(text) => Some(OsStr::new(text)),
+                Part::FieldCode('f' | 'u' | 'F' | 'U') => targets.first().map(OsString::as_os_str),
+                Part::FieldCode('i') => context.icon.as_deref().map(OsStr::new),
+                Part::FieldCode('c') => context.name.as_deref().map(OsStr::new),
+                Part::FieldCode('k') => context.location.as_deref().map(Path::as_os_str),
+                Part::FieldCode('%') => Some(OsStr::new("%")),
+                // Deprecated and unknown field codes are removed.
+                Part::FieldCode(_) => None,
+            };
+            if let Some(value) = value {
+                expanded.push(value);
+                keep = true;
+            }
+        }
+
+        if keep {
+            argv.push(expanded);
+        }
+    }
+
+    argv
+}
+
+/// Splits an unescaped `Exec` value into arguments. Arguments are separated by spaces and may be
+/// quoted with double quotes, inside which a backslash escapes the next character. Returns `None`
+/// if a quote isn't closed.
+///
+/// Field codes are recognized inside quotes too, as GLib does, so that `%%` always stands for `%`.
+fn split_exec(exec: &str) -> Option<Vec<Vec<Part>>> {
+    fn push_text(arg: &mut Vec<Part>, c: char) {
+        match arg.last_mut() {
+            Some(Part::Text(text)) => text.push(c),
+            _ => arg.push(Part::Text(c.to_string())),
+        }
+    }
+
+    fn push_field_code(arg: &mut Vec<Part>, chars: &mut std::str::Chars<'_>) {
+        if let Some(code) = chars.next() {
+            arg.push(Part::FieldCode(code));
+        }
+    }
+
+    let mut args = Vec::new();
+    let mut arg = Vec::new();
+    let mut in_arg = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            '"' => {
+                in_arg = true;
+                // Keeps `""` as an empty argument.
+                arg.push(Part::Text(String::new()));
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => push_text(&mut arg, chars.next()?),
+                        '%' => push_field_code(&mut arg, &mut chars),
+                        c => push_text(&mut arg, c),
+                    }
+                }
+            }
+            '%' => {
+                in_arg = true;
+                push_field_code(&mut arg, &mut chars);
+            }
+            c => {
+                in_arg = true;
+                push_text(&mut arg, c);
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(arg);
+    }
+
+    Some(args)
+}
+
+/// Looks up a localized key in a group. See [`DesktopEntry::get_localized`].
+fn localized(key_file: &KeyFile, group: &str, key: &str, locale: Option<&str>) -> Option<String> {
+    let locale = match locale {
+        Some(locale) => Some(locale.to_owned()),
+        None => current_locale(),
+    };
+
+    locale
+        .as_deref()
+        .map(locale_variants)
+        .unwrap_or_default()
+        .iter()
+        .find_map(|variant| key_file.get(group, &format!("{key}[{variant}]")))
+        .or_else(|| key_file.get(group, key))
+        .map(unescape)
+}
+
+fn current_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()))
+}
+
+/// The locale names to try for `lang_COUNTRY.ENCODING@MODIFIER`, most specific first:
+/// `lang_COUNTRY@MODIFIER`, `lang_COUNTRY`, `lang@MODIFIER` and `lang`.
+fn locale_variants(locale: &str) -> Vec<String> {
+    let (rest, modifier) = match locale.split_once('@') {
+        Some((rest, modifier)) => (rest, Some(modifier)),
+        None => (locale, None),
+    };
+    let rest = rest.split('.').next().unwrap_or_default();
+    let (lang, country) = match rest.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (rest, None),
+    };
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut variants = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        variants.push(format!("{lang}_{country}@{modifier}"));
+    }
+    if let Some(country) = country {
+        variants.push(format!("{lang}_{country}"));
+    }
+    if let Some(modifier) = modifier {
+        variants.push(format!("{lang}@{modifier}"));
+    }
+    variants.push(lang.to_owned());
+    variants
+}
+
+/// Builds a command from a command line, wrapped in a terminal emulator if `terminal` is set and
+/// one is installed.
+fn command_for(argv: Vec<OsString>, terminal: bool) -> Command {
+    let terminal = if terminal {
+        TERMINALS
+            .iter()
+            .find(|(name, _)| find_program(OsStr::new(name)).is_some())
+    } else {
+        None
+    };
+
+    let mut argv = argv.into_iter();
+    let mut cmd = match terminal {
+        Some((name, args)) => {
+            let mut cmd = Command::new(name);
+            cmd.args(*args);
+            cmd
+        }
+        None => Command::new(argv.next().unwrap_or_default()),
+    };
+    cmd.args(argv);
+    cmd
+}
+
+/// Finds an executable file, given either as a path or as a name to look up in `$PATH`.
+pub(crate) fn find_program(program: &OsStr) -> Option<PathBuf> {
+    find_program_in(program, env::var_os("PATH").as_deref())
+}
+
+/// Like [`find_program`], but searches the directories of `path` instead of `$PATH`.
+pub(crate) fn find_program_in(program: &OsStr, path: Option<&OsStr>) -> Option<PathBuf> {
+    let is_executable = |path: &Path| {
+        fs::metadata(path)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    };
+
+    let program = Path::new(program);
+    if program.components().count() > 1 {
+        return is_executable(program).then(|| program.to_owned());
+    }
+
+    env::split_paths(path?)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+/// Recursively searches `dir` for the file whose desktop file ID is `id`. Files in subdirectories
+/// have IDs prefixed with the subdirectory names joined by `-`.
+fn find_in_dir(dir: &Path, prefix: &str, id: &str) -> Option<PathBuf> {
+    let candidate = dir.join(&id[prefix.len()..]);
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .find_map(|entry| {
+            let name = entry.file_name();
+            let prefix = format!("{prefix}{}-", name.to_str()?);
+            if id.starts_with(&prefix) {
+                find_in_dir(&entry.path(), &prefix, id)
+            } else {
+                None
+            }
+        })
+}
+
+/// Replaces the escape sequences allowed in values of type string.
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
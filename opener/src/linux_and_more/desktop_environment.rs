@@ -0,0 +1,195 @@
+//! Detection of the desktop environment, following the `detectDE` function of `xdg-open`.
+
+use super::desktop_entry::find_program;
+use crate::{Environment, Package};
+use std::env;
+use std::ffi::OsStr;
+use std::fmt::{self, Display, Formatter};
+
+/// A desktop environment, as detected by [`desktop_environment`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DesktopEnvironment {
+    /// KDE Plasma.
+    Kde {
+        /// The major version, from `$KDE_SESSION_VERSION`.
+        version: Option<u32>,
+    },
+
+    /// GNOME.
+    Gnome {
+        /// The major version: `Some(2)` if GNOME 2's `gnome-default-applications-properties` is
+        /// installed, otherwise `None`, meaning GNOME 3 or later.
+        version: Option<u32>,
+    },
+
+    /// Cinnamon.
+    Cinnamon,
+
+    /// Xfce.
+    Xfce,
+
+    /// MATE.
+    Mate,
+
+    /// LXDE.
+    Lxde,
+
+    /// LXQt.
+    Lxqt,
+
+    /// Deepin.
+    Deepin,
+
+    /// Enlightenment.
+    Enlightenment,
+
+    /// A session that asks for generic behavior, with `XDG_CURRENT_DESKTOP=X-Generic`.
+    Generic,
+
+    /// A Flatpak sandbox, in which requests go through the desktop portal rather than the desktop
+    /// environment.
+    Flatpak,
+
+    /// Another desktop environment, named by `$XDG_CURRENT_DESKTOP`. `xdg-open` treats these like
+    /// [`Generic`](DesktopEnvironment::Generic).
+    Other(String),
+}
+
+impl DesktopEnvironment {
+    /// The major version of the desktop environment, if it is known.
+    pub fn version(&self) -> Option<u32> {
+        match self {
+            DesktopEnvironment::Kde { version } | DesktopEnvironment::Gnome { version } => *version,
+            _ => None,
+        }
+    }
+}
+
+impl Display for DesktopEnvironment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DesktopEnvironment::Kde { .. } => "KDE",
+            DesktopEnvironment::Gnome { .. } => "GNOME",
+            DesktopEnvironment::Cinnamon => "Cinnamon",
+            DesktopEnvironment::Xfce => "XFCE",
+            DesktopEnvironment::Mate => "MATE",
+            DesktopEnvironment::Lxde => "LXDE",
+            DesktopEnvironment::Lxqt => "LXQt",
+            DesktopEnvironment::Deepin => "Deepin",
+            DesktopEnvironment::Enlightenment => "Enlightenment",
+            DesktopEnvironment::Generic => "X-Generic",
+            DesktopEnvironment::Flatpak => "Flatpak",
+            DesktopEnvironment::Other(name) => name,
+        };
+        f.write_str(name)?;
+
+        match self.version() {
+            Some(version) => write!(f, " {version}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Detects the desktop environment of the current session, the way `xdg-open` does to decide how
+/// to open paths. Returns `None` if it can't be determined, in which case `xdg-open` uses its
+/// generic method.
+///
+/// These are checked in order:
+///
+/// 1. `$XDG_CURRENT_DESKTOP`, when it's one of the values `xdg-open` recognizes, such as `KDE`,
+///    `GNOME`, `GNOME-Classic:GNOME`, `XFCE` or `X-Cinnamon`.
+/// 2. Variables set by particular desktop environments: `$KDE_FULL_SESSION`,
+///    `$GNOME_DESKTOP_SESSION_ID`, `$MATE_DESKTOP_SESSION_ID`, `$DESKTOP` and
+///    `$LXQT_SESSION_CONFIG`.
+/// 3. `$DESKTOP_SESSION`.
+/// 4. Any other `$XDG_CURRENT_DESKTOP` value, returned as
+///    [`Other`](DesktopEnvironment::Other).
+///
+/// Inside a Flatpak sandbox, as detected by [`Environment::current`],
+/// [`Flatpak`](DesktopEnvironment::Flatpak) is returned instead. Use
+/// [`DesktopEnvironment::detect`] to pass another [`Environment`]. Unlike
+/// `xdg-open`, no programs are run to query D-Bus or X11 window properties, so those fallbacks are
+/// not used.
+///
+/// ```no_run
+/// use opener::DesktopEnvironment;
+///
+/// match opener::desktop_environment() {
+///     Some(DesktopEnvironment::Kde { version }) => println!("KDE {version:?}"),
+///     Some(other) => println!("{other}"),
+///     None => println!("unknown desktop environment"),
+/// }
+/// ```
+pub fn desktop_environment() -> Option<DesktopEnvironment> {
+    DesktopEnvironment::detect(Environment::current())
+}
+
+impl DesktopEnvironment {
+    /// Detects the desktop environment like [`desktop_environment`], in the given runtime
+    /// environment rather than the current one.
+    pub fn detect(environment: &Environment) -> Option<DesktopEnvironment> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+
+        if environment.package() == Some(Package::Flatpak) {
+            return Some(DesktopEnvironment::Flatpak);
+        }
+
+        let current_desktop = var("XDG_CURRENT_DESKTOP");
+        let detected = current_desktop
+            .as_deref()
+            .and_then(from_current_desktop)
+            .or_else(|| {
+                if var("KDE_FULL_SESSION").is_some() {
+                    Some(DesktopEnvironment::Kde { version: None })
+                } else if var("GNOME_DESKTOP_SESSION_ID").is_some() {
+                    Some(DesktopEnvironment::Gnome { version: None })
+                } else if var("MATE_DESKTOP_SESSION_ID").is_some() {
+                    Some(DesktopEnvironment::Mate)
+                } else if var("DESKTOP").is_some_and(|desktop| desktop.starts_with("Enlightenment"))
+                {
+                    Some(DesktopEnvironment::Enlightenment)
+                } else if var("LXQT_SESSION_CONFIG").is_some() {
+                    Some(DesktopEnvironment::Lxqt)
+                } else {
+                    None
+                }
+            })
+            .or_else(|| match var("DESKTOP_SESSION")?.as_str() {
+                "gnome" => Some(DesktopEnvironment::Gnome { version: None }),
+                "LXDE" | "Lubuntu" => Some(DesktopEnvironment::Lxde),
+                "MATE" => Some(DesktopEnvironment::Mate),
+                "xfce" | "xfce4" | "Xfce Session" => Some(DesktopEnvironment::Xfce),
+                _ => None,
+            })
+            .or_else(|| current_desktop.map(DesktopEnvironment::Other))?;
+
+        Some(match detected {
+            DesktopEnvironment::Kde { .. } => DesktopEnvironment::Kde {
+                version: var("KDE_SESSION_VERSION").and_then(|version| version.parse().ok()),
+            },
+            DesktopEnvironment::Gnome { .. } => DesktopEnvironment::Gnome {
+                version: find_program(OsStr::new("gnome-default-applications-properties"))
+                    .map(|_| 2),
+            },
+            other => other,
+        })
+    }
+}
+
+fn from_current_desktop(current_desktop: &str) -> Option<DesktopEnvironment> {
+    Some(match current_desktop {
+        "Cinnamon" | "X-Cinnamon" => DesktopEnvironment::Cinnamon,
+        "ENLIGHTENMENT" => DesktopEnvironment::Enlightenment,
+        // GNOME, GNOME-Classic:GNOME or GNOME-Flashback:GNOME.
+        gnome if gnome.starts_with("GNOME") => DesktopEnvironment::Gnome { version: None },
+        "KDE" => DesktopEnvironment::Kde { version: None },
+        "DEEPIN" | "Deepin" | "deepin" => DesktopEnvironment::Deepin,
+        "LXDE" => DesktopEnvironment::Lxde,
+        "LXQt" => DesktopEnvironment::Lxqt,
+        "MATE" => DesktopEnvironment::Mate,
+        "XFCE" => DesktopEnvironment::Xfce,
+        "X-Generic" => DesktopEnvironment::Generic,
+        _ => return None,
+    })
+}
@@ -0,0 +1,55 @@
+//! Parsing of the INI-like key file format shared by `.desktop` files, `mimeapps.list` and
+//! `mimeinfo.cache`.
+
+/// A parsed key file. Groups and keys are kept in file order, and values are not unescaped.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct KeyFile {
+    groups: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl KeyFile {
+    pub(crate) fn parse(contents: &str) -> KeyFile {
+        let mut groups: Vec<(String, Vec<(String, String)>)> = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                groups.push((header.to_owned(), Vec::new()));
+            } else if let (Some((_, keys)), Some((key, value))) =
+                (groups.last_mut(), line.split_once('='))
+            {
+                keys.push((key.trim().to_owned(), value.trim().to_owned()));
+            }
+        }
+
+        KeyFile { groups }
+    }
+
+    /// Iterates over the keys of a group, in file order. If the group appears more than once, only
+    /// the first occurrence is used.
+    pub(crate) fn group(&self, name: &str) -> impl Iterator<Item = (&str, &str)> {
+        self.groups
+            .iter()
+            .find(|(group, _)| group == name)
+            .into_iter()
+            .flat_map(|(_, keys)| keys.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+    }
+
+    /// Gets the value of a key. If the key appears more than once in the group, the first value is
+    /// used.
+    pub(crate) fn get(&self, group: &str, key: &str) -> Option<&str> {
+        self.group(group).find(|&(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+/// Splits a value of type string(s), which is a list separated by `;` with an optional trailing
+/// `;`. Empty items are skipped.
+pub(crate) fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
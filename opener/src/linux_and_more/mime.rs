@@ -0,0 +1,629 @@
+//! MIME type detection using the [shared-mime-info] database.
+//!
+//! [shared-mime-info]: https://specifications.freedesktop.org/shared-mime-info-spec/latest/
+
+use super::xdg_dirs;
+use crate::OpenError;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// How many bytes are read to tell text from binary files.
+const SNIFF_LEN: u64 = 512;
+
+/// Determines the MIME type of a file the way `xdg-mime query filetype` does, using the
+/// [shared-mime-info] database found in `$XDG_DATA_HOME` and `$XDG_DATA_DIRS`.
+///
+/// Directories and other special files get `inode/*` types, such as `inode/directory` or
+/// `inode/fifo`. For regular files, the `globs2` patterns are matched against the file name, and
+/// the `magic` rules against the contents when no pattern matches or several types match equally
+/// well. Files matching neither are `application/x-zerosize` if they are empty, and `text/plain` or
+/// `application/octet-stream` otherwise, depending on whether their start looks like text.
+///
+/// The database is read once, when it's first needed, and kept for the lifetime of the program.
+///
+/// Symbolic links are followed. Returns an error if the file's metadata or contents can't be read.
+///
+/// ```no_run
+/// # fn main() -> Result<(), opener::OpenError> {
+/// assert_eq!(opener::mime_type("/tmp")?, "inode/directory");
+/// println!("{}", opener::mime_type("Cargo.toml")?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [shared-mime-info]: https://specifications.freedesktop.org/shared-mime-info-spec/latest/
+pub fn mime_type<P>(path: P) -> Result<String, OpenError>
+where
+    P: AsRef<Path>,
+{
+    detect(path.as_ref()).map_err(OpenError::Io)
+}
+
+fn detect(path: &Path) -> io::Result<String> {
+    let metadata = fs::metadata(path)?;
+    let file_type = metadata.file_type();
+    let special = if file_type.is_dir() {
+        Some("inode/directory")
+    } else if file_type.is_char_device() {
+        Some("inode/chardevice")
+    } else if file_type.is_block_device() {
+        Some("inode/blockdevice")
+    } else if file_type.is_fifo() {
+        Some("inode/fifo")
+    } else if file_type.is_socket() {
+        Some("inode/socket")
+    } else {
+        None
+    };
+    if let Some(mime_type) = special {
+        return Ok(mime_type.to_owned());
+    }
+
+    let candidates = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| Globs::get().mime_types(name))
+        .unwrap_or_default();
+    if let [mime_type] = &candidates[..] {
+        return Ok(mime_type.clone());
+    }
+
+    let magic = Magic::get();
+    let mut head = Vec::new();
+    File::open(path)?
+        .take(magic.extent().max(SNIFF_LEN))
+        .read_to_end(&mut head)?;
+
+    let sniffed = magic.mime_type(&head);
+    if let Some(first) = candidates.first() {
+        // Several patterns matched equally well: prefer the one the contents agree with.
+        let agrees = |candidate: &&String| {
+            sniffed.is_some_and(|sniffed| {
+                *candidate == sniffed || parents(candidate).iter().any(|p| p == sniffed)
+            })
+        };
+        return Ok(candidates.iter().find(agrees).unwrap_or(first).clone());
+    }
+
+    Ok(match sniffed {
+        Some(mime_type) => mime_type.to_owned(),
+        None if head.is_empty() => "application/x-zerosize".to_owned(),
+        None if looks_like_text(&head) => "text/plain".to_owned(),
+        None => "application/octet-stream".to_owned(),
+    })
+}
+
+/// Lists the types a MIME type is a subclass of, most specific first. Applications that handle a
+/// parent type can handle the subclass too.
+pub(crate) fn parents(mime_type: &str) -> Vec<String> {
+    static SUBCLASSES: OnceLock<Vec<String>> = OnceLock::new();
+    let subclasses = SUBCLASSES.get_or_init(|| {
+        mime_dirs()
+            .filter_map(|dir| fs::read_to_string(dir.join("subclasses")).ok())
+            .collect()
+    });
+
+    let mut parents: Vec<String> = Vec::new();
+    let mut queue = vec![mime_type.to_owned()];
+    while let Some(current) = queue.pop() {
+        for contents in subclasses {
+            for line in contents.lines() {
+                if let Some((child, parent)) = line.split_once(' ') {
+                    if child == current
+                        && parent != mime_type
+                        && !parents.iter().any(|p| p == parent)
+                    {
+                        parents.push(parent.to_owned());
+                        queue.insert(0, parent.to_owned());
+                    }
+                }
+            }
+        }
+    }
+
+    // All text types are implicitly subclasses of text/plain, and all types are implicitly
+    // subclasses of application/octet-stream.
+    let implicit = if mime_type.starts_with("text/") {
+        &["text/plain", "application/octet-stream"][..]
+    } else {
+        &["application/octet-stream"][..]
+    };
+    for parent in implicit {
+        if *parent != mime_type && !parents.iter().any(|p| p == parent) {
+            parents.push((*parent).to_owned());
+        }
+    }
+
+    parents
+}
+
+/// Determines the MIME types to look up handlers for, most specific first, and the argument to
+/// pass to the handler. `file:` URLs are converted to paths, other URLs get an
+/// `x-scheme-handler/<scheme>` type, and relative paths are resolved against `current_dir`.
+pub(crate) fn target_mime_types(
+    target: &OsStr,
+    current_dir: Option<&Path>,
+) -> io::Result<(Vec<String>, OsString)> {
+    match url_scheme(target) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("file") => {
+            let path = file_url_to_path(target).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "unsupported file URL")
+            })?;
+            Ok((path_mime_types(Path::new(&path), current_dir)?, path))
+        }
+        Some(scheme) => Ok((
+            vec![format!("x-scheme-handler/{}", scheme.to_ascii_lowercase())],
+            target.to_owned(),
+        )),
+        None => Ok((
+            path_mime_types(Path::new(target), current_dir)?,
+            target.to_owned(),
+        )),
+    }
+}
+
+/// The MIME type of a file, followed by its parent types.
+fn path_mime_types(path: &Path, current_dir: Option<&Path>) -> io::Result<Vec<String>> {
+    let resolved;
+    let path = match current_dir {
+        Some(dir) if path.is_relative() => {
+            resolved = dir.join(path);
+            &resolved
+        }
+        _ => path,
+    };
+
+    let mime_type = detect(path)?;
+    let parents = parents(&mime_type);
+    Ok([mime_type].into_iter().chain(parents).collect())
+}
+
+/// Gets the scheme of a URL, using the same rule as `xdg-open`: anything starting with a letter
+/// followed by letters, digits, `+`, `-` or `.` and then a `:` is treated as a URL.
+pub(crate) fn url_scheme(target: &OsStr) -> Option<&str> {
+    let bytes = target.as_bytes();
+    let colon = bytes.iter().position(|&b| b == b':')?;
+    let scheme = &bytes[..colon];
+    let valid = scheme.first().is_some_and(u8::is_ascii_alphabetic)
+        && scheme
+            .iter()
+            .all(|&b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'));
+
+    if valid {
+        std::str::from_utf8(scheme).ok()
+    } else {
+        None
+    }
+}
+
+/// Converts a `file:` URL of a local file into a path, decoding percent-encoded bytes.
+fn file_url_to_path(url: &OsStr) -> Option<OsString> {
+    let rest = &url.as_bytes()["file:".len()..];
+    let path = match rest.strip_prefix(b"//") {
+        Some(authority_and_path) => {
+            let slash = authority_and_path.iter().position(|&b| b == b'/')?;
+            let host = &authority_and_path[..slash];
+            if !(host.is_empty() || host.eq_ignore_ascii_case(b"localhost")) {
+                return None;
+            }
+            &authority_and_path[slash..]
+        }
+        None => rest,
+    };
+
+    // Queries and fragments aren't part of the path.
+    let path = path.split(|&b| b == b'?' || b == b'#').next()?;
+
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut bytes = path.iter();
+    while let Some(&b) = bytes.next() {
+        if b == b'%' {
+            let hex = [*bytes.next()?, *bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            decoded.push(b);
+        }
+    }
+
+    Some(OsString::from_vec(decoded))
+}
+
+/// The `mime` subdirectories of the XDG data directories, most preferred first.
+fn mime_dirs() -> impl Iterator<Item = PathBuf> {
+    xdg_dirs::all_data_dirs()
+        .into_iter()
+        .map(|dir| dir.join("mime"))
+}
+
+/// The patterns from the `globs2` files.
+#[derive(Debug)]
+struct Globs {
+    globs: Vec<Glob>,
+}
+
+#[derive(Debug)]
+struct Glob {
+    weight: u32,
+    mime_type: String,
+    pattern: String,
+    case_sensitive: bool,
+}
+
+impl Globs {
+    /// The patterns, loaded on first use.
+    fn get() -> &'static Globs {
+        static GLOBS: OnceLock<Globs> = OnceLock::new();
+        GLOBS.get_or_init(Globs::load)
+    }
+
+    fn load() -> Globs {
+        let mut globs: Vec<Glob> = Vec::new();
+
+        // Less preferred directories are read first, so that `__NOGLOBS__` entries in more
+        // preferred directories can discard their patterns.
+        let dirs: Vec<PathBuf> = mime_dirs().collect();
+        for dir in dirs.iter().rev() {
+            let Ok(contents) = fs::read_to_string(dir.join("globs2")) else {
+                continue;
+            };
+
+            for line in contents.lines() {
+                if line.starts_with('#') {
+                    continue;
+                }
+
+                let mut fields = line.split(':');
+                let (Some(weight), Some(mime_type), Some(pattern)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                if pattern == "__NOGLOBS__" {
+                    globs.retain(|glob| glob.mime_type != mime_type);
+                    continue;
+                }
+
+                let flags = fields.next().unwrap_or_default();
+                globs.push(Glob {
+                    weight: weight.parse().unwrap_or(50),
+                    mime_type: mime_type.to_owned(),
+                    pattern: pattern.to_owned(),
+                    case_sensitive: flags.split(',').any(|flag| flag == "cs"),
+                });
+            }
+        }
+
+        Globs { globs }
+    }
+
+    /// Finds the best matching MIME types for a file name. Literal patterns beat wildcard
+    /// patterns, then higher weights win, then longer patterns. More than one type is returned when
+    /// several patterns match equally well.
+    fn mime_types(&self, file_name: &str) -> Vec<String> {
+        let lowercase_name = file_name.to_lowercase();
+        let mut matches: Vec<_> = self
+            .globs
+            .iter()
+            .filter(|glob| {
+                if glob.case_sensitive {
+                    glob_matches(&glob.pattern, file_name)
+                } else {
+                    glob_matches(&glob.pattern.to_lowercase(), &lowercase_name)
+                }
+            })
+            .map(|glob| {
+                let key = (
+                    !glob.pattern.contains(['*', '?', '[']),
+                    glob.weight,
+                    glob.pattern.len(),
+                );
+                (key, &glob.mime_type)
+            })
+            .collect();
+
+        let Some(best) = matches.iter().map(|(key, _)| *key).max() else {
+            return Vec::new();
+        };
+        matches.retain(|(key, _)| *key == best);
+
+        let mut mime_types: Vec<String> = Vec::new();
+        for (_, mime_type) in matches {
+            if !mime_types.contains(mime_type) {
+                mime_types.push(mime_type.clone());
+            }
+        }
+        mime_types
+    }
+}
+
+/// The rules from the `magic` files, which recognize file types from their contents.
+#[derive(Debug)]
+struct Magic {
+    /// Sorted by decreasing priority.
+    sections: Vec<MagicSection>,
+}
+
+#[derive(Debug)]
+struct MagicSection {
+    priority: u32,
+    mime_type: String,
+    matchlets: Vec<Matchlet>,
+}
+
+/// A single rule of a section. A matchlet matches if its value is found, and either it has no
+/// children (the following matchlets with a deeper indent) or one of them matches too.
+#[derive(Debug)]
+struct Matchlet {
+    indent: usize,
+    offset: usize,
+    value: Vec<u8>,
+    mask: Option<Vec<u8>>,
+    range_length: usize,
+}
+
+impl Magic {
+    /// The rules, loaded on first use.
+    fn get() -> &'static Magic {
+        static MAGIC: OnceLock<Magic> = OnceLock::new();
+        MAGIC.get_or_init(Magic::load)
+    }
+
+    fn load() -> Magic {
+        let mut sections: Vec<(usize, MagicSection)> = Vec::new();
+
+        // As with globs, `__NOMAGIC__` in a more preferred directory discards the rules of less
+        // preferred ones.
+        let dirs: Vec<PathBuf> = mime_dirs().collect();
+        for (rank, dir) in dirs.iter().enumerate().rev() {
+            let Ok(contents) = fs::read(dir.join("magic")) else {
+                continue;
+            };
+            for (section, no_magic) in parse_magic(&contents) {
+                if no_magic {
+                    sections.retain(|(_, s)| s.mime_type != section.mime_type);
+                }
+                sections.push((rank, section));
+            }
+        }
+
+        // Among equal priorities, more preferred directories win.
+        sections.sort_by_key(|(rank, section)| (std::cmp::Reverse(section.priority), *rank));
+        Magic {
+            sections: sections.into_iter().map(|(_, section)| section).collect(),
+        }
+    }
+
+    /// How many bytes from the start of a file the rules look at.
+    fn extent(&self) -> u64 {
+        self.sections
+            .iter()
+            .flat_map(|section| &section.matchlets)
+            .map(|m| m.offset + m.range_length - 1 + m.value.len())
+            .max()
+            .unwrap_or(0) as u64
+    }
+
+    /// Finds the highest priority type whose rules match the start of a file.
+    fn mime_type(&self, head: &[u8]) -> Option<&str> {
+        self.sections
+            .iter()
+            .find(|section| matchlets_match(&section.matchlets, 0, head))
+            .map(|section| section.mime_type.as_str())
+    }
+}
+
+/// Whether any of the matchlets at `indent` at the start of `matchlets` matches, along with one of
+/// its children.
+fn matchlets_match(matchlets: &[Matchlet], indent: usize, head: &[u8]) -> bool {
+    let mut i = 0;
+    while i < matchlets.len() && matchlets[i].indent >= indent {
+        let children_end = i
+            + 1
+            + matchlets[i + 1..]
+                .iter()
+                .take_while(|m| m.indent > indent)
+                .count();
+        let children = &matchlets[i + 1..children_end];
+        if matchlets[i].indent == indent
+            && matchlets[i].matches(head)
+            && (children.is_empty() || matchlets_match(children, indent + 1, head))
+        {
+            return true;
+        }
+        i = children_end;
+    }
+
+    false
+}
+
+impl Matchlet {
+    fn matches(&self, head: &[u8]) -> bool {
+        (self.offset..self.offset + self.range_length).any(|start| {
+            let Some(bytes) = head.get(start..start + self.value.len()) else {
+                return false;
+            };
+            match &self.mask {
+                Some(mask) => bytes
+                    .iter()
+                    .zip(&self.value)
+                    .zip(mask)
+                    .all(|((b, v), m)| b & m == v & m),
+                None => bytes == self.value,
+            }
+        })
+    }
+}
+
+/// Parses a `magic` file into its sections, each with whether it contained `__NOMAGIC__`. Parsing
+/// stops at the first malformed line, keeping the sections read so far.
+fn parse_magic(contents: &[u8]) -> Vec<(MagicSection, bool)> {
+    let mut sections = Vec::new();
+    let Some(mut rest) = contents.strip_prefix(b"MIME-Magic\0\n") else {
+        return sections;
+    };
+
+    while !rest.is_empty() {
+        if let Some(header) = rest.strip_prefix(b"[") {
+            let Some(end) = header.iter().position(|&b| b == b'\n') else {
+                break;
+            };
+            let Some((priority, mime_type)) = std::str::from_utf8(&header[..end])
+                .ok()
+                .and_then(|h| h.strip_suffix(']'))
+                .and_then(|h| h.split_once(':'))
+            else {
+                break;
+            };
+            sections.push((
+                MagicSection {
+                    priority: priority.parse().unwrap_or(50),
+                    mime_type: mime_type.to_owned(),
+                    matchlets: Vec::new(),
+                },
+                false,
+            ));
+            rest = &header[end + 1..];
+            continue;
+        }
+
+        let Some((section, no_magic)) = sections.last_mut() else {
+            break;
+        };
+        if let Some(after) = rest.strip_prefix(b"__NOMAGIC__\n") {
+            *no_magic = true;
+            rest = after;
+            continue;
+        }
+        match parse_matchlet(rest) {
+            Some((matchlet, after)) => {
+                section.matchlets.push(matchlet);
+                rest = after;
+            }
+            None => break,
+        }
+    }
+
+    sections
+}
+
+/// Parses a line of the form `[indent]>start-offset=value[&mask][~word-size][+range-length]`, where
+/// `value` is preceded by its length as a big-endian 16-bit number, and `mask` has the same length.
+fn parse_matchlet(line: &[u8]) -> Option<(Matchlet, &[u8])> {
+    fn number(bytes: &[u8]) -> (Option<usize>, &[u8]) {
+        let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+        let number = std::str::from_utf8(&bytes[..digits])
+            .ok()
+            .and_then(|n| n.parse().ok());
+        (number, &bytes[digits..])
+    }
+
+    let (indent, rest) = number(line);
+    let rest = rest.strip_prefix(b">")?;
+    let (offset, rest) = number(rest);
+    let rest = rest.strip_prefix(b"=")?;
+    let (len, rest) = rest.split_first_chunk::<2>()?;
+    let len = u16::from_be_bytes(*len) as usize;
+    let (value, mut rest) = rest.split_at_checked(len)?;
+
+    let mut matchlet = Matchlet {
+        indent: indent.unwrap_or(0),
+        offset: offset?,
+        value: value.to_owned(),
+        mask: None,
+        range_length: 1,
+    };
+    let mut word_size = 1;
+    if let Some(after) = rest.strip_prefix(b"&") {
+        let (mask, after) = after.split_at_checked(len)?;
+        matchlet.mask = Some(mask.to_owned());
+        rest = after;
+    }
+    if let Some(after) = rest.strip_prefix(b"~") {
+        let (size, after) = number(after);
+        word_size = size?;
+        rest = after;
+    }
+    if let Some(after) = rest.strip_prefix(b"+") {
+        let (range_length, after) = number(after);
+        matchlet.range_length = range_length?.max(1);
+        rest = after;
+    }
+    let rest = rest.strip_prefix(b"\n")?;
+
+    // Values and masks of multi-byte words are stored big-endian.
+    if cfg!(target_endian = "little") && word_size > 1 {
+        let swap =
+            |bytes: &mut Vec<u8>| bytes.chunks_mut(word_size).for_each(|word| word.reverse());
+        swap(&mut matchlet.value);
+        if let Some(mask) = &mut matchlet.mask {
+            swap(mask);
+        }
+    }
+
+    Some((matchlet, rest))
+}
+
+/// Matches a file name against a shell glob pattern supporting `*`, `?` and `[...]`.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    glob_matches_chars(&pattern, &name)
+}
+
+fn glob_matches_chars(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|i| glob_matches_chars(rest, &name[i..])),
+        Some(('?', rest)) => !name.is_empty() && glob_matches_chars(rest, &name[1..]),
+        Some(('[', rest)) => {
+            let Some((&c, name_rest)) = name.split_first() else {
+                return false;
+            };
+            let Some(end) = rest.iter().skip(1).position(|&p| p == ']').map(|i| i + 1) else {
+                // An unterminated class is matched literally.
+                return c == '[' && glob_matches_chars(rest, name_rest);
+            };
+
+            let (negated, class) = match rest[..end].split_first() {
+                Some(('!' | '^', class)) => (true, class),
+                _ => (false, &rest[..end]),
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+
+            matched != negated && glob_matches_chars(&rest[end + 1..], name_rest)
+        }
+        Some((&p, rest)) => name.first() == Some(&p) && glob_matches_chars(rest, &name[1..]),
+    }
+}
+
+/// Guesses whether the start of a file is text, the way shared-mime-info implementations do when
+/// no pattern matches: text files are valid UTF-8 and contain no control characters other than
+/// whitespace.
+fn looks_like_text(head: &[u8]) -> bool {
+    let valid_utf8 = match std::str::from_utf8(head) {
+        Ok(_) => true,
+        // The read may have cut a multi-byte character in half.
+        Err(err) => err.error_len().is_none(),
+    };
+
+    valid_utf8
+        && !head
+            .iter()
+            .any(|&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+}
@@ -0,0 +1,338 @@
+//! Default application lookup, as described by the [MIME Applications Associations
+//! Specification].
+//!
+//! [MIME Applications Associations Specification]: https://specifications.freedesktop.org/mime-apps-spec/latest/
+
+use super::desktop_entry::{self, DesktopEntry};
+use super::key_file::{split_list, KeyFile};
+use super::xdg_dirs;
+use crate::OpenError;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) const DEFAULT_APPLICATIONS: &str = "Default Applications";
+pub(crate) const ADDED_ASSOCIATIONS: &str = "Added Associations";
+const REMOVED_ASSOCIATIONS: &str = "Removed Associations";
+const MIME_CACHE: &str = "MIME Cache";
+
+/// The association files found on the system, in order of precedence.
+#[derive(Debug)]
+pub(crate) struct MimeApps {
+    sources: Vec<Source>,
+}
+
+#[derive(Debug)]
+enum Source {
+    /// A `mimeapps.list` file.
+    List(KeyFile),
+
+    /// A `mimeinfo.cache` file, generated by `update-desktop-database` from the `MimeType` keys
+    /// of the desktop entries in its directory.
+    Cache(KeyFile),
+}
+
+impl MimeApps {
+    /// Reads the association files from the locations given by the specification.
+    pub(crate) fn load() -> MimeApps {
+        let desktops = xdg_dirs::current_desktops();
+        let list_names: Vec<String> = desktops
+            .iter()
+            .map(|desktop| format!("{desktop}-mimeapps.list"))
+            .chain(["mimeapps.list".to_owned()])
+            .collect();
+
+        let config_dirs = xdg_dirs::config_home()
+            .into_iter()
+            .chain(xdg_dirs::config_dirs());
+        let data_dirs = xdg_dirs::all_data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("applications"));
+
+        let mut sources = Vec::new();
+        for dir in config_dirs {
+            sources.extend(read_lists(&dir, &list_names));
+        }
+        for dir in data_dirs {
+            sources.extend(read_lists(&dir, &list_names));
+            if let Some(cache) = read_key_file(&dir.join("mimeinfo.cache")) {
+                sources.push(Source::Cache(cache));
+            }
+        }
+
+        MimeApps { sources }
+    }
+
+    /// Finds the default application for a MIME type, falling back to the most preferred
+    /// associated application when no default is set. Only installed applications are considered.
+    pub(crate) fn default_application(&self, mime_type: &str) -> Option<DesktopEntry> {
+        self.sources
+            .iter()
+            .filter_map(|source| match source {
+                Source::List(list) => list.get(DEFAULT_APPLICATIONS, mime_type),
+                Source::Cache(_) => None,
+            })
+            .flat_map(split_list)
+            .find_map(find_installed)
+            .or_else(|| {
+                self.associations(mime_type)
+                    .iter()
+                    .find_map(|id| find_installed(id))
+            })
+    }
+
+    /// Lists the installed applications that can open a file with the given MIME types, which are
+    /// ordered from most to least specific. The default applications come first, followed by the
+    /// associated applications of each type.
+    pub(crate) fn applications(&self, mime_types: &[String]) -> Vec<DesktopEntry> {
+        let defaults = mime_types
+            .iter()
+            .filter_map(|mime_type| self.default_application(mime_type));
+        let associated = mime_types
+            .iter()
+            .flat_map(|mime_type| self.associations(mime_type))
+            .filter_map(|id| find_installed(&id));
+
+        let mut applications: Vec<DesktopEntry> = Vec::new();
+        for entry in defaults.chain(associated) {
+            if !applications.iter().any(|a| a.id() == entry.id()) {
+                applications.push(entry);
+            }
+        }
+        applications
+    }
+
+    /// Lists the desktop file IDs of the applications associated with a MIME type, most preferred
+    /// first. Added associations take precedence over `mimeinfo.cache` entries from the same or
+    /// lower-precedence directories, and removed associations hide entries from lower-precedence
+    /// directories.
+    pub(crate) fn associations(&self, mime_type: &str) -> Vec<String> {
+        let mut associations = Vec::new();
+        let mut removed = HashSet::new();
+        for source in &self.sources {
+            let (added, removed_here) = match source {
+                Source::List(list) => (
+                    list.get(ADDED_ASSOCIATIONS, mime_type),
+                    list.get(REMOVED_ASSOCIATIONS, mime_type),
+                ),
+                Source::Cache(cache) => (cache.get(MIME_CACHE, mime_type), None),
+            };
+
+            for id in added.into_iter().flat_map(split_list) {
+                if !removed.contains(id) && !associations.iter().any(|a| a == id) {
+                    associations.push(id.to_owned());
+                }
+            }
+            removed.extend(removed_here.into_iter().flat_map(split_list));
+        }
+
+        associations
+    }
+}
+
+/// Makes an application the default for a MIME type, by setting it in the `[Default Applications]`
+/// group of the user's `$XDG_CONFIG_HOME/mimeapps.list`, as `xdg-mime default` does.
+///
+/// `mime_type` is a MIME type such as `text/markdown`, or `x-scheme-handler/<scheme>` for the
+/// handler of a URL scheme. `desktop_id` is the [desktop file ID] of the application; the
+/// `.desktop` suffix is optional. The application doesn't have to be installed yet.
+///
+/// Other groups, keys and comments of the file are kept as they are, and the file is replaced
+/// atomically, so that readers never see a partially written file. If the file is a symbolic link,
+/// its target is replaced.
+///
+/// ```no_run
+/// # fn main() -> Result<(), opener::OpenError> {
+/// opener::set_default_application("application/x-ourformat", "com.example.Viewer")?;
+/// opener::set_default_application("x-scheme-handler/ourapp", "com.example.Viewer")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [desktop file ID]: https://specifications.freedesktop.org/desktop-entry-spec/latest/file-naming.html#desktop-file-id
+pub fn set_default_application(mime_type: &str, desktop_id: &str) -> Result<(), OpenError> {
+    set_default(mime_type, desktop_id).map_err(OpenError::Io)
+}
+
+/// [`set_default_application`], for callers that handle `io::Error`s.
+pub(crate) fn set_default(mime_type: &str, desktop_id: &str) -> io::Result<()> {
+    let valid_mime_type = mime_type
+        .split_once('/')
+        .is_some_and(|(kind, subtype)| !kind.is_empty() && !subtype.is_empty())
+        && !mime_type.contains(['=', '[', ']', ';', '\n', '\r']);
+    if !valid_mime_type {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid MIME type '{mime_type}'"),
+        ));
+    }
+
+    let desktop_id = desktop_entry::normalize_id(desktop_id)?;
+
+    update_user_list(|contents| {
+        set_key(
+            contents,
+            DEFAULT_APPLICATIONS,
+            mime_type,
+            &format!("{desktop_id};"),
+        )
+    })
+}
+
+/// Rewrites the user's `mimeapps.list` with `update`, which gets the current contents (empty if the
+/// file doesn't exist yet).
+pub(crate) fn update_user_list(update: impl FnOnce(&str) -> String) -> io::Result<()> {
+    let config_home = xdg_dirs::config_home().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "neither $XDG_CONFIG_HOME nor $HOME is set",
+        )
+    })?;
+    let mut path = config_home.join("mimeapps.list");
+    if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+        path = fs::canonicalize(&path)?;
+    }
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    write_atomically(&path, update(&contents).as_bytes())
+}
+
+/// Writes a file by writing a temporary file next to it and renaming it over the original.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(dir)?;
+
+    // The counter keeps the name unique between threads of this process.
+    static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut temp_name = PathBuf::from(path.file_name().unwrap_or_default()).into_os_string();
+    temp_name.push(format!(
+        ".{}.{}.tmp",
+        process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp_path = dir.join(temp_name);
+
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            // Keep the permissions of the file being replaced, such as a private `mimeapps.list`.
+            match fs::metadata(path) {
+                Ok(metadata) => file.set_permissions(metadata.permissions())?,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Sets a key in a key file, keeping everything else as it is. An existing key is replaced in
+/// place; otherwise the key is added at the end of the group, which is created at the end of the
+/// file if needed.
+pub(crate) fn set_key(contents: &str, group: &str, key: &str, value: &str) -> String {
+    let entry = format!("{key}={value}");
+    let mut lines: Vec<&str> = contents.lines().collect();
+
+    let header = lines.iter().position(|line| {
+        line.trim()
+            .strip_prefix('[')
+            .and_then(|l| l.strip_suffix(']'))
+            == Some(group)
+    });
+    match header {
+        Some(header) => {
+            let end = lines[header + 1..]
+                .iter()
+                .position(|line| line.trim_start().starts_with('['))
+                .map_or(lines.len(), |i| header + 1 + i);
+            let existing = (header + 1..end).find(|&i| {
+                lines[i]
+                    .split_once('=')
+                    .is_some_and(|(k, _)| k.trim() == key)
+            });
+            match existing {
+                Some(i) => lines[i] = &entry,
+                None => {
+                    // After the last key, before any blank lines separating the next group.
+                    let insert_at = (header + 1..end)
+                        .rev()
+                        .find(|&i| !lines[i].trim().is_empty())
+                        .map_or(header + 1, |i| i + 1);
+                    lines.insert(insert_at, &entry);
+                }
+            }
+        }
+        None => {
+            let group_header = format!("[{group}]");
+            let mut result = lines.join("\n");
+            if !result.trim().is_empty() {
+                result.push_str("\n\n");
+            } else {
+                result.clear();
+            }
+            result.push_str(&group_header);
+            result.push('\n');
+            result.push_str(&entry);
+            result.push('\n');
+            return result;
+        }
+    }
+
+    let mut result = lines.join("\n");
+    result.push('\n');
+    result
+}
+
+/// Removes an item from the list value of a key in a key file, keeping everything else as it is.
+/// The key is removed when its list becomes empty.
+pub(crate) fn remove_from_list(contents: &str, group: &str, key: &str, item: &str) -> String {
+    let mut in_group = false;
+    let mut result = String::with_capacity(contents.len());
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_group = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) == Some(group);
+        } else if let Some((k, value)) = line.split_once('=').filter(|_| in_group) {
+            if k.trim() == key && split_list(value).any(|i| i == item) {
+                let remaining: Vec<&str> = split_list(value).filter(|&i| i != item).collect();
+                if !remaining.is_empty() {
+                    result.push_str(&format!("{key}={};\n", remaining.join(";")));
+                }
+                continue;
+            }
+        }
+
+        result.push_str(line);
+        result.push('\n');
+    }
+
+    result
+}
+
+fn read_lists<'a>(dir: &'a Path, names: &'a [String]) -> impl Iterator<Item = Source> + 'a {
+    names
+        .iter()
+        .filter_map(|name| read_key_file(&dir.join(name)))
+        .map(Source::List)
+}
+
+fn read_key_file(path: &Path) -> Option<KeyFile> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|contents| KeyFile::parse(&contents))
+}
+
+fn find_installed(id: &str) -> Option<DesktopEntry> {
+    DesktopEntry::find(id).filter(DesktopEntry::is_installed)
+}
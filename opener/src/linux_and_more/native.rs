@@ -0,0 +1,36 @@
+//! A replacement for `xdg-open` that looks up and launches the default application itself, used
+//! with the `native-xdg` feature. Unlike the `xdg-open` script, it needs no shell, and behaves the
+//! same in every desktop environment.
+
+use super::mimeapps::MimeApps;
+use super::{mime, spawn};
+use crate::{OpenError, OpenOptions};
+use std::ffi::OsStr;
+use std::process::{Child, Stdio};
+
+/// Launches the default application for `target`, returning the name of its program along with
+/// the process.
+pub(crate) fn open(
+    target: &OsStr,
+    wait: bool,
+    options: &OpenOptions,
+) -> Result<(String, Child), OpenError> {
+    let (mime_types, arg) =
+        mime::target_mime_types(target, options.current_dir_path()).map_err(OpenError::Io)?;
+
+    let mime_apps = MimeApps::load();
+    let entry = mime_types
+        .iter()
+        .find_map(|mime_type| mime_apps.default_application(mime_type))
+        .ok_or_else(|| OpenError::NoApplication {
+            mime_type: mime_types[0].clone(),
+        })?;
+
+    let mut cmd = entry.command(&arg).map_err(OpenError::Io)?;
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(crate::stderr_for(wait));
+    let cmd_name = cmd.get_program().to_string_lossy().into_owned();
+    let child = spawn(cmd, &cmd_name, options)?;
+    Ok((cmd_name, child))
+}
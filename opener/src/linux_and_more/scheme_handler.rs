@@ -0,0 +1,278 @@
+//! Registration of applications as handlers of URL schemes, through desktop entries with an
+//! `x-scheme-handler/<scheme>` MIME type.
+
+use super::mimeapps::{self, ADDED_ASSOCIATIONS, DEFAULT_APPLICATIONS};
+use super::{desktop_entry, mime, xdg_dirs};
+use crate::OpenError;
+use std::ffi::{OsStr, OsString};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::{env, fs, io};
+
+/// Registers an application as the handler of a URL scheme, such as `ourapp://` links, for the
+/// current user.
+///
+/// [`register`](UrlSchemeHandler::register) writes a desktop entry to
+/// `$XDG_DATA_HOME/applications` whose `MimeType` is `x-scheme-handler/<scheme>`, refreshes the
+/// desktop database of that directory, and makes the entry the default for the scheme in
+/// `mimeapps.list`. Links are then opened with the application by [`open`](crate::open), by the
+/// `xdg-open` script and by desktop environments, with the URL passed as the last argument.
+///
+/// ```no_run
+/// # fn main() -> Result<(), opener::OpenError> {
+/// use opener::UrlSchemeHandler;
+///
+/// // Runs the current executable as `<exe> --open-url <url>`.
+/// UrlSchemeHandler::new("ourapp", "com.example.OurApp")
+///     .name("Our App")
+///     .arg("--open-url")
+///     .register()?;
+///
+/// // Later, when uninstalling:
+/// UrlSchemeHandler::new("ourapp", "com.example.OurApp").unregister()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct UrlSchemeHandler {
+    scheme: String,
+    desktop_id: String,
+    name: Option<String>,
+    program: Option<OsString>,
+    args: Vec<OsString>,
+}
+
+impl UrlSchemeHandler {
+    /// Describes the handler of `scheme` (without the `:`), installed as the desktop entry with the
+    /// [desktop file ID] `desktop_id`. The `.desktop` suffix is optional.
+    ///
+    /// By default, the handler runs the current executable with the URL as its only argument.
+    ///
+    /// [desktop file ID]: https://specifications.freedesktop.org/desktop-entry-spec/latest/file-naming.html#desktop-file-id
+    pub fn new(scheme: &str, desktop_id: &str) -> Self {
+        Self {
+            scheme: scheme.to_owned(),
+            desktop_id: desktop_id.to_owned(),
+            name: None,
+            program: None,
+            args: Vec::new(),
+        }
+    }
+
+    /// Sets the application name shown by desktop environments, such as when asking which
+    /// application to open a link with. Defaults to the desktop file ID.
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    /// Sets the program to run, instead of the current executable. It should be an absolute path,
+    /// or a name found in `$PATH`.
+    pub fn program<S>(&mut self, program: S) -> &mut Self
+    where
+        S: AsRef<OsStr>,
+    {
+        self.program = Some(program.as_ref().to_owned());
+        self
+    }
+
+    /// Adds an argument passed to the program before the URL.
+    pub fn arg<S>(&mut self, arg: S) -> &mut Self
+    where
+        S: AsRef<OsStr>,
+    {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds multiple arguments passed to the program before the URL.
+    pub fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    /// Writes the desktop entry and makes it the default handler of the scheme, replacing any
+    /// entry previously written with the same ID. Returns the path of the desktop entry.
+    ///
+    /// Refreshing the desktop database with `update-desktop-database` is skipped if it isn't
+    /// installed, since the default set in `mimeapps.list` is enough for the link to be opened.
+    pub fn register(&self) -> Result<PathBuf, OpenError> {
+        self.write_entry().map_err(OpenError::Io)
+    }
+
+    /// Removes the desktop entry written by [`register`](UrlSchemeHandler::register), and removes
+    /// it from the defaults and associations of the scheme in the user's `mimeapps.list`. Doesn't
+    /// fail if the handler isn't registered.
+    pub fn unregister(&self) -> Result<(), OpenError> {
+        self.remove_entry().map_err(OpenError::Io)
+    }
+
+    fn write_entry(&self) -> io::Result<PathBuf> {
+        let mime_type = self.mime_type()?;
+        let desktop_id = desktop_entry::normalize_id(&self.desktop_id)?;
+        let program = match &self.program {
+            Some(program) => program.clone(),
+            None => env::current_exe()?.into_os_string(),
+        };
+
+        let mut exec = String::new();
+        for arg in [&program].into_iter().chain(&self.args) {
+            exec.push_str(&quote_exec_arg(arg)?);
+            exec.push(' ');
+        }
+        exec.push_str("%u");
+
+        let name = self
+            .name
+            .as_deref()
+            .unwrap_or_else(|| desktop_id.trim_end_matches(".desktop"));
+        let contents = format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name={}\n\
+             Exec={}\n\
+             MimeType={mime_type};\n\
+             NoDisplay=true\n\
+             Terminal=false\n",
+            escape_value(name),
+            escape_value(&exec),
+        );
+
+        let dir = applications_dir()?;
+        let path = dir.join(&desktop_id);
+        mimeapps::write_atomically(&path, contents.as_bytes())?;
+        update_desktop_database(&dir);
+
+        mimeapps::set_default(&mime_type, &desktop_id)?;
+        Ok(path)
+    }
+
+    fn remove_entry(&self) -> io::Result<()> {
+        let mime_type = self.mime_type()?;
+        let desktop_id = desktop_entry::normalize_id(&self.desktop_id)?;
+
+        let dir = applications_dir()?;
+        match fs::remove_file(dir.join(&desktop_id)) {
+            Ok(()) => update_desktop_database(&dir),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        mimeapps::update_user_list(|contents| {
+            [DEFAULT_APPLICATIONS, ADDED_ASSOCIATIONS].iter().fold(
+                contents.to_owned(),
+                |contents, group| {
+                    mimeapps::remove_from_list(&contents, group, &mime_type, &desktop_id)
+                },
+            )
+        })
+    }
+
+    /// The `x-scheme-handler/<scheme>` MIME type, with the scheme lowercased, since schemes are
+    /// case-insensitive.
+    fn mime_type(&self) -> io::Result<String> {
+        let with_colon = format!("{}:", self.scheme);
+        if mime::url_scheme(OsStr::new(&with_colon)) != Some(self.scheme.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid URL scheme '{}'", self.scheme),
+            ));
+        }
+
+        Ok(format!(
+            "x-scheme-handler/{}",
+            self.scheme.to_ascii_lowercase()
+        ))
+    }
+}
+
+fn applications_dir() -> io::Result<PathBuf> {
+    xdg_dirs::data_home()
+        .map(|dir| dir.join("applications"))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "neither $XDG_DATA_HOME nor $HOME is set",
+            )
+        })
+}
+
+/// Regenerates the `mimeinfo.cache` of a directory of desktop entries. This is best-effort: the
+/// cache only speeds up lookups, and isn't needed when a default is set.
+fn update_desktop_database(dir: &std::path::Path) {
+    let _ = Command::new("update-desktop-database")
+        .arg("-q")
+        .arg(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+/// Quotes an argument for an `Exec` key if it contains reserved characters, and escapes `%` as
+/// `%%`. Arguments have to be valid UTF-8.
+fn quote_exec_arg(arg: &OsStr) -> io::Result<String> {
+    let arg = arg.to_str().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{arg:?} isn't valid UTF-8"),
+        )
+    })?;
+
+    let needs_quotes = arg.is_empty()
+        || arg.contains(|c: char| {
+            c.is_whitespace()
+                || matches!(
+                    c,
+                    '"' | '\''
+                        | '\\'
+                        | '>'
+                        | '<'
+                        | '~'
+                        | '|'
+                        | '&'
+                        | ';'
+                        | '$'
+                        | '*'
+                        | '?'
+                        | '#'
+                        | '('
+                        | ')'
+                        | '`'
+                )
+        });
+    let arg = arg.replace('%', "%%");
+    if !needs_quotes {
+        return Ok(arg);
+    }
+
+    let mut quoted = String::from('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    Ok(quoted)
+}
+
+/// Escapes a value of type string for a key file.
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,57 @@
+//! Base directories from the [XDG Base Directory Specification].
+//!
+//! [XDG Base Directory Specification]: https://specifications.freedesktop.org/basedir-spec/latest/
+
+use std::env;
+use std::path::PathBuf;
+
+/// `$XDG_DATA_HOME`, defaulting to `$HOME/.local/share`.
+pub(crate) fn data_home() -> Option<PathBuf> {
+    home_dir_var("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$XDG_DATA_DIRS`, defaulting to `/usr/local/share:/usr/share`.
+pub(crate) fn data_dirs() -> Vec<PathBuf> {
+    dirs_var("XDG_DATA_DIRS", "/usr/local/share:/usr/share")
+}
+
+/// The user data directory followed by the system data directories, in order of preference.
+pub(crate) fn all_data_dirs() -> Vec<PathBuf> {
+    data_home().into_iter().chain(data_dirs()).collect()
+}
+
+/// `$XDG_CONFIG_HOME`, defaulting to `$HOME/.config`.
+pub(crate) fn config_home() -> Option<PathBuf> {
+    home_dir_var("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_CONFIG_DIRS`, defaulting to `/etc/xdg`.
+pub(crate) fn config_dirs() -> Vec<PathBuf> {
+    dirs_var("XDG_CONFIG_DIRS", "/etc/xdg")
+}
+
+/// The lowercased names in `$XDG_CURRENT_DESKTOP`, used to find desktop-specific configuration.
+pub(crate) fn current_desktops() -> Vec<String> {
+    env::var("XDG_CURRENT_DESKTOP")
+        .unwrap_or_default()
+        .split(':')
+        .filter(|name| !name.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+fn home_dir_var(var: &str, home_relative_default: &str) -> Option<PathBuf> {
+    match env::var_os(var) {
+        Some(dir) if PathBuf::from(&dir).is_absolute() => Some(dir.into()),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(home_relative_default)),
+    }
+}
+
+fn dirs_var(var: &str, default: &str) -> Vec<PathBuf> {
+    let value = env::var_os(var)
+        .filter(|value| !value.is_empty())
+        .unwrap_or_else(|| default.into());
+    env::split_paths(&value)
+        .filter(|dir| dir.is_absolute())
+        .collect()
+}
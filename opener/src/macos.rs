@@ -1,30 +1,159 @@
-use crate::OpenError;
+use crate::{Application, Launched, Launcher, OpenError, OpenOptions};
 use std::ffi::OsStr;
 use std::process::{Command, Stdio};
 
-pub(crate) fn open(path: &OsStr) -> Result<(), OpenError> {
-    let mut open = Command::new("open")
+pub(crate) fn default_launchers(_options: &OpenOptions) -> Vec<Launcher> {
+    vec![Launcher::MacOpen]
+}
+
+pub(crate) fn is_launcher_available(launcher: &Launcher, _options: &OpenOptions) -> bool {
+    launcher.is_supported()
+}
+
+pub(crate) fn open(path: &OsStr, options: &OpenOptions) -> Result<Launched, OpenError> {
+    options.try_launchers(path, &default_launchers(options), |_| {
+        open_with_open(path, options)
+    })
+}
+
+fn open_with_open(path: &OsStr, options: &OpenOptions) -> Result<Launched, OpenError> {
+    let mut cmd = Command::new("open");
+    cmd.arg(path).stdin(Stdio::null()).stdout(Stdio::null());
+    run_open(cmd, options)
+}
+
+pub(crate) fn open_with(
+    path: &OsStr,
+    app: &OsStr,
+    options: &OpenOptions,
+) -> Result<Launched, OpenError> {
+    let mut cmd = Command::new("open");
+    cmd.arg("-a")
+        .arg(app)
+        .arg("--")
         .arg(path)
         .stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(OpenError::Io)?;
+        .stdout(Stdio::null());
+    run_open(cmd, options)
+}
+
+pub(crate) fn open_with_chooser(
+    _path: &OsStr,
+    _options: &OpenOptions,
+) -> Result<Launched, OpenError> {
+    Err(OpenError::Unsupported {
+        operation: "choosing an application",
+    })
+}
+
+/// Not implemented yet: Launch Services would be needed to list the applications for a path.
+pub(crate) fn applications_for(_path: &OsStr) -> Result<Vec<Application>, OpenError> {
+    Ok(Vec::new())
+}
 
-    crate::wait_child(&mut open, "open")
+#[cfg(feature = "reveal")]
+pub(crate) fn default_reveal_launchers(_options: &OpenOptions) -> Vec<Launcher> {
+    vec![Launcher::MacOpen]
 }
 
 #[cfg(feature = "reveal")]
-pub(crate) fn reveal(path: &std::path::Path) -> Result<(), OpenError> {
-    let mut open = Command::new("open")
-        .arg("-R")
+pub(crate) fn reveal_with_launcher(
+    launcher: &Launcher,
+    paths: &[&std::path::Path],
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    if *launcher != Launcher::MacOpen {
+        return Err(OpenError::Unsupported {
+            operation: "revealing paths with this launcher",
+        });
+    }
+
+    let mut cmd = Command::new("open");
+    cmd.arg("-R")
         .arg("--")
-        .arg(path)
+        .args(paths)
         .stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(OpenError::Io)?;
+        .stdout(Stdio::null());
+    run_open(cmd, options).map(|_| ())
+}
+
+#[cfg(feature = "reveal")]
+pub(crate) fn open_folder_with_launcher(
+    launcher: &Launcher,
+    path: &std::path::Path,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    if *launcher != Launcher::MacOpen {
+        return Err(OpenError::Unsupported {
+            operation: "opening folders with this launcher",
+        });
+    }
+    open_with(path.as_os_str(), OsStr::new("Finder"), options).map(|_| ())
+}
+
+/// Asks Finder to open the "Get Info" window. The path is passed as an argument of the script, so
+/// it doesn't need to be escaped.
+#[cfg(feature = "reveal")]
+pub(crate) fn show_properties(
+    path: &std::path::Path,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    let mut cmd = Command::new("osascript");
+    cmd.args([
+        "-e",
+        "on run argv",
+        "-e",
+        "tell application \"Finder\"",
+        "-e",
+        "open information window of (POSIX file (item 1 of argv) as alias)",
+        "-e",
+        "activate",
+        "-e",
+        "end tell",
+        "-e",
+        "end run",
+        "--",
+    ])
+    .arg(path)
+    .stdin(Stdio::null())
+    .stdout(Stdio::null());
+    let wait = options.should_wait(true);
+    cmd.stderr(crate::stderr_for(wait));
+    options.configure(&mut cmd);
+    let osascript = cmd.spawn().map_err(|err| OpenError::Spawn {
+        cmds: "osascript".into(),
+        source: err,
+    })?;
+    crate::finish_child(osascript, "osascript", wait)
+}
+
+fn run_open(mut cmd: Command, options: &OpenOptions) -> Result<Launched, OpenError> {
+    let wait = options.should_wait(true);
+    cmd.stderr(crate::stderr_for(wait));
+    options.configure(&mut cmd);
+    let open = cmd.spawn().map_err(|err| OpenError::Spawn {
+        cmds: "open".into(),
+        source: err,
+    })?;
+
+    let pid = open.id();
+    crate::finish_child(open, "open", wait).map_err(open_error)?;
+
+    Ok(Launched::new(Launcher::MacOpen, Some(pid), wait))
+}
 
-    crate::wait_child(&mut open, "open")
+/// Recognizes the failures `open` reports on stderr, since it exits with status 1 for all of them.
+fn open_error(err: OpenError) -> OpenError {
+    match err {
+        OpenError::ExitStatus { cmd, stderr, .. } if stderr.contains("does not exist") => {
+            OpenError::FileNotFound { cmd, stderr }
+        }
+        // -10814 is kLSApplicationNotFoundErr.
+        OpenError::ExitStatus { cmd, stderr, .. }
+            if stderr.contains("No application knows how to open") || stderr.contains("-10814") =>
+        {
+            OpenError::NoHandler { cmd, stderr }
+        }
+        err => err,
+    }
 }
@@ -0,0 +1,587 @@
+use crate::{sys, Backend, Environment, Launched, Launcher, OpenError, WindowIdentifier};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+/// Options and flags which can be used to configure how a path is opened.
+///
+/// The [`open`], [`open_with`], [`open_browser`] and [`reveal`] functions use the default options. Create an
+/// `OpenOptions` with [`OpenOptions::new`] to change them, chain calls to the setter methods, then
+/// call [`open`](OpenOptions::open), [`open_with`](OpenOptions::open_with),
+/// [`open_browser`](OpenOptions::open_browser) or [`reveal`](OpenOptions::reveal):
+///
+/// ```no_run
+/// # fn main() -> Result<(), ::opener::OpenError> {
+/// use opener::{Launcher, OpenOptions};
+///
+/// OpenOptions::new()
+///     .current_dir("/tmp")
+///     .env("LANG", "C")
+///     .wait(true)
+///     .prefer(Launcher::XdgOpen)
+///     .open("report.pdf")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`open`]: crate::open
+/// [`open_with`]: crate::open_with
+/// [`open_browser`]: crate::open_browser
+/// [`reveal`]: crate::reveal
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    current_dir: Option<PathBuf>,
+    envs: Vec<(OsString, OsString)>,
+    wait: Option<bool>,
+    preferred: Option<Launcher>,
+    environment: Option<Environment>,
+    ask: bool,
+    writable: bool,
+    activation_token: Option<String>,
+    parent_window: Option<WindowIdentifier>,
+    backends: Vec<Arc<dyn Backend>>,
+    launchers: Option<Vec<Launcher>>,
+    #[cfg(feature = "reveal")]
+    reveal_launchers: Option<Vec<Launcher>>,
+    disabled: Vec<Launcher>,
+}
+
+impl OpenOptions {
+    /// Creates a blank set of options, equivalent to what the [`open`](crate::open) function uses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the working directory of the launcher process.
+    ///
+    /// Relative paths passed to [`open`](OpenOptions::open) are resolved against this directory.
+    /// Has no effect on backends that don't spawn a process, such as D-Bus.
+    pub fn current_dir<P>(&mut self, dir: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.current_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Adds an environment variable to the launcher process's environment.
+    ///
+    /// The variable is inherited by the launched program where the launcher passes its environment
+    /// on. Has no effect on Windows, or on backends that don't spawn a process, such as D-Bus.
+    ///
+    /// A `PATH` set here is also where launchers such as `xdg-open` are looked up.
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.envs
+            .push((key.as_ref().to_owned(), val.as_ref().to_owned()));
+        self
+    }
+
+    /// Adds multiple environment variables to the launcher process's environment.
+    ///
+    /// See [`env`](OpenOptions::env).
+    pub fn envs<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        for (key, val) in vars {
+            self.env(key, val);
+        }
+        self
+    }
+
+    /// Sets whether to wait for the launcher process to exit, and check its exit status.
+    ///
+    /// By default, this depends on the launcher: `wslview` and the macOS `open` command are waited
+    /// for, while `xdg-open` is not, because some `xdg-open` implementations don't exit until the
+    /// launched program does. Has no effect on Windows.
+    ///
+    /// When `xdg-open` is waited for, its documented exit codes are reported as
+    /// [`OpenError::InvalidArguments`], [`OpenError::FileNotFound`], [`OpenError::NoHandler`] and
+    /// [`OpenError::ActionFailed`], and if it fails, the next launcher is tried.
+    ///
+    /// Launcher processes that aren't waited for are reaped by a background thread once they exit,
+    /// so they don't accumulate as zombie processes in long-running programs.
+    ///
+    /// This also sets whether to wait for the response of desktop portal requests, which is only
+    /// sent once the request has been handled. It's only waited for by default when asking the
+    /// user to choose an application, since a portal may take arbitrarily long to respond.
+    pub fn wait(&mut self, wait: bool) -> &mut Self {
+        self.wait = Some(wait);
+        self
+    }
+
+    /// Sets the launcher to try first.
+    ///
+    /// The remaining launchers the platform would normally use are still tried afterwards, in
+    /// their usual order. Launchers that don't apply to the current platform are ignored.
+    pub fn prefer(&mut self, launcher: Launcher) -> &mut Self {
+        self.preferred = Some(launcher);
+        self
+    }
+
+    /// Registers a custom [`Backend`], which is tried before the built-in launchers, as
+    /// [`Launcher::Custom`] with its name. Backends are tried in the order they're registered.
+    pub fn backend<B>(&mut self, backend: B) -> &mut Self
+    where
+        B: Backend + 'static,
+    {
+        self.backends.push(Arc::new(backend));
+        self
+    }
+
+    /// Sets the exact launchers to try, in order, instead of the platform's usual ones followed by
+    /// the custom backends.
+    ///
+    /// Launchers that aren't listed aren't tried. Custom backends are listed as
+    /// [`Launcher::Custom`] with their name, and have to be registered with
+    /// [`backend`](OpenOptions::backend) as well. Launchers that don't apply to the current
+    /// platform are ignored.
+    ///
+    /// Revealing paths and opening folders, with the **reveal** feature, follow `reveal_launchers`
+    /// instead.
+    pub fn launchers<I>(&mut self, launchers: I) -> &mut Self
+    where
+        I: IntoIterator<Item = Launcher>,
+    {
+        self.launchers = Some(launchers.into_iter().collect());
+        self
+    }
+
+    /// Sets the exact launchers to try, in order, when revealing paths and opening folders in the
+    /// file manager, instead of the custom backends followed by the platform's usual ones. On
+    /// Linux, these are [`Launcher::FileManager1`], [`Launcher::Portal`] and
+    /// [`Launcher::ContainingFolder`], or [`Launcher::Explorer`] under WSL.
+    ///
+    /// Launchers that aren't listed aren't tried, so listing only a custom backend replaces the
+    /// built-in ones. Launchers that can't reveal paths on the current platform are ignored, and
+    /// those that can't open folders, such as [`Launcher::ContainingFolder`], are skipped by
+    /// [`open_folder`](OpenOptions::open_folder).
+    #[cfg(feature = "reveal")]
+    pub fn reveal_launchers<I>(&mut self, launchers: I) -> &mut Self
+    where
+        I: IntoIterator<Item = Launcher>,
+    {
+        self.reveal_launchers = Some(launchers.into_iter().collect());
+        self
+    }
+
+    /// Prevents a launcher, built-in or custom, from being tried, both when opening and when
+    /// revealing paths.
+    pub fn disable(&mut self, launcher: Launcher) -> &mut Self {
+        self.disabled.push(launcher);
+        self
+    }
+
+    /// The launchers that [`open`](OpenOptions::open) would try, in order, leaving out those that
+    /// aren't available, such as programs that aren't installed.
+    pub fn available_launchers(&self) -> Vec<Launcher> {
+        self.launcher_order(&sys::default_launchers(self))
+            .into_iter()
+            .filter(|launcher| {
+                matches!(launcher, Launcher::Custom(_))
+                    || sys::is_launcher_available(launcher, self)
+            })
+            .collect()
+    }
+
+    /// Sets the runtime environment to make backend decisions for, such as whether to use Windows
+    /// programs under WSL, instead of [`Environment::current`].
+    ///
+    /// This is mostly useful for testing how paths would be opened elsewhere.
+    pub fn environment(&mut self, environment: Environment) -> &mut Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Sets whether to let the user choose the application in a system dialog, instead of using
+    /// the default application.
+    ///
+    /// On Linux, this requires the **portal** feature: the `ask` option of the
+    /// `org.freedesktop.portal.OpenURI` desktop portal is set, and the portal is tried first even
+    /// outside of a sandbox. On Windows, the "Open with" dialog is shown. Has no effect on Mac.
+    ///
+    /// See also [`open_with_chooser`](OpenOptions::open_with_chooser).
+    pub fn ask(&mut self, ask: bool) -> &mut Self {
+        self.ask = ask;
+        self
+    }
+
+    /// Sets whether the application opening a file may write to it, when the file is opened
+    /// through the desktop portal.
+    ///
+    /// This sets the `writable` option of the `org.freedesktop.portal.OpenURI` portal, which
+    /// matters when the application is itself sandboxed. Has no effect on other platforms, or
+    /// without the **portal** feature.
+    pub fn writable(&mut self, writable: bool) -> &mut Self {
+        self.writable = writable;
+        self
+    }
+
+    /// Sets the [XDG activation] token, or X11 startup notification ID, to pass on to the launched
+    /// program, so that its window can be raised and focused.
+    ///
+    /// Wayland compositors only let a program activate its window with a token, such as one
+    /// requested by the program opening the path in response to user input. Without one, windows
+    /// may open behind the current one. The token is set as the `XDG_ACTIVATION_TOKEN` and
+    /// `DESKTOP_STARTUP_ID` environment variables of launcher processes, passed in the
+    /// `activation_token` option of the desktop portal, and as the startup ID of
+    /// `org.freedesktop.FileManager1` when revealing paths. Has no effect on Windows or Mac.
+    ///
+    /// [XDG activation]: https://wayland.app/protocols/xdg-activation-v1
+    pub fn activation_token<S>(&mut self, token: S) -> &mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.activation_token = Some(token.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the window that dialogs of the desktop portal, such as the application chooser, should
+    /// be modal to.
+    ///
+    /// Used when opening paths through the portal with the **portal** feature, and when revealing
+    /// paths through it. Has no effect on other platforms or backends.
+    pub fn parent_window(&mut self, window: WindowIdentifier) -> &mut Self {
+        self.parent_window = Some(window);
+        self
+    }
+
+    /// Opens a file or link with the system default program, using these options.
+    ///
+    /// Unlike the [`open`](crate::open) function, this returns a [`Launched`] describing how the
+    /// path was opened. See the [`open`](crate::open) function for details.
+    pub fn open<P>(&self, path: P) -> Result<Launched, OpenError>
+    where
+        P: AsRef<OsStr>,
+    {
+        sys::open(path.as_ref(), self)
+    }
+
+    /// Opens a file or link with a specific application, using these options, and returns a
+    /// [`Launched`] describing how it was launched.
+    ///
+    /// See the [`open_with`](crate::open_with) function for details.
+    pub fn open_with<P, A>(&self, path: P, app: A) -> Result<Launched, OpenError>
+    where
+        P: AsRef<OsStr>,
+        A: AsRef<OsStr>,
+    {
+        sys::open_with(path.as_ref(), app.as_ref(), self)
+    }
+
+    /// Lets the user choose an application in a system dialog, then opens a file or link with it,
+    /// using these options. Returns a [`Launched`] describing how the dialog was shown.
+    ///
+    /// See the [`open_with_chooser`](crate::open_with_chooser) function for details.
+    pub fn open_with_chooser<P>(&self, path: P) -> Result<Launched, OpenError>
+    where
+        P: AsRef<OsStr>,
+    {
+        sys::open_with_chooser(path.as_ref(), self)
+    }
+
+    /// Opens a file or link with the system default program, using the `BROWSER` environment
+    /// variable when set, and these options. Returns a [`Launched`] describing how the path was
+    /// opened.
+    ///
+    /// See the [`open_browser`](crate::open_browser) function for details.
+    pub fn open_browser<P>(&self, path: P) -> Result<Launched, OpenError>
+    where
+        P: AsRef<OsStr>,
+    {
+        let mut path = path.as_ref();
+        if let Ok(browser_var) = env::var("BROWSER") {
+            let windows_path;
+            if self.runtime_environment().is_wsl() && browser_var.ends_with(".exe") {
+                if let Some(windows_path_2) = crate::wsl_to_windows_path(path) {
+                    windows_path = windows_path_2;
+                    path = &windows_path;
+                }
+            };
+
+            let wait = self.should_wait(false);
+            let mut cmd = Command::new(&browser_var);
+            cmd.arg(path)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(crate::stderr_for(wait));
+            self.configure(&mut cmd);
+            let child = cmd.spawn().map_err(|err| OpenError::Spawn {
+                cmds: browser_var,
+                source: err,
+            })?;
+
+            let pid = child.id();
+            crate::finish_child(child, "$BROWSER", wait)?;
+
+            Ok(Launched::new(Launcher::Browser, Some(pid), wait))
+        } else {
+            sys::open(path, self)
+        }
+    }
+
+    /// Opens the default file explorer and reveals a file or folder in its containing folder,
+    /// using these options.
+    ///
+    /// See the [`reveal`](crate::reveal) function for details.
+    #[cfg(feature = "reveal")]
+    pub fn reveal<P>(&self, path: P) -> Result<(), OpenError>
+    where
+        P: AsRef<Path>,
+    {
+        self.reveal_many([path])
+    }
+
+    /// Opens the default file explorer and reveals several files or folders at once, using these
+    /// options.
+    ///
+    /// See the [`reveal_many`](crate::reveal_many) function for details.
+    #[cfg(feature = "reveal")]
+    pub fn reveal_many<I, P>(&self, paths: I) -> Result<(), OpenError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let paths: Vec<P> = paths.into_iter().collect();
+        let paths: Vec<&Path> = paths.iter().map(AsRef::as_ref).collect();
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        self.try_reveal_launchers(
+            "revealing paths",
+            |backend| backend.reveal(&paths, self),
+            |launcher| sys::reveal_with_launcher(launcher, &paths, self),
+        )
+    }
+
+    /// Opens a folder in the file manager, using these options.
+    ///
+    /// See the [`open_folder`](crate::open_folder) function for details.
+    #[cfg(feature = "reveal")]
+    pub fn open_folder<P>(&self, path: P) -> Result<(), OpenError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if !path.metadata().map_err(OpenError::Io)?.is_dir() {
+            return Err(OpenError::Io(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the path is not a directory",
+            )));
+        }
+        self.try_reveal_launchers(
+            "opening a folder in the file manager",
+            |backend| backend.open_folder(path, self),
+            |launcher| sys::open_folder_with_launcher(launcher, path, self),
+        )
+    }
+
+    /// Shows the file manager's properties dialog for a file or folder, using these options.
+    ///
+    /// See the [`show_properties`](crate::show_properties) function for details.
+    #[cfg(feature = "reveal")]
+    pub fn show_properties<P>(&self, path: P) -> Result<(), OpenError>
+    where
+        P: AsRef<Path>,
+    {
+        sys::show_properties(path.as_ref(), self)
+    }
+
+    /// Applies the working directory, activation token and environment variables to a launcher
+    /// command.
+    /// The `PATH` that launcher processes are spawned with: the last one set with
+    /// [`env`](OpenOptions::env), or else our own.
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    pub(crate) fn path_var(&self) -> Option<OsString> {
+        self.envs
+            .iter()
+            .rev()
+            .find(|(key, _)| key == "PATH")
+            .map(|(_, val)| val.clone())
+            .or_else(|| env::var_os("PATH"))
+    }
+
+    pub(crate) fn configure(&self, cmd: &mut Command) {
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+        if let Some(token) = &self.activation_token {
+            cmd.env("XDG_ACTIVATION_TOKEN", token)
+                .env("DESKTOP_STARTUP_ID", token);
+        }
+        cmd.envs(self.envs.iter().map(|(key, val)| (key, val)));
+    }
+
+    #[cfg(any(
+        target_os = "windows",
+        all(feature = "native-xdg", not(target_os = "macos")),
+        all(feature = "portal", target_os = "linux")
+    ))]
+    pub(crate) fn current_dir_path(&self) -> Option<&Path> {
+        self.current_dir.as_deref()
+    }
+
+    #[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+    pub(crate) fn startup_id(&self) -> Option<&str> {
+        self.activation_token.as_deref()
+    }
+
+    /// The `parent_window` argument of portal methods, which is empty when there's none.
+    #[cfg(all(any(feature = "reveal", feature = "portal"), target_os = "linux"))]
+    pub(crate) fn parent_window_id(&self) -> String {
+        self.parent_window
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+
+    /// The environment set with [`environment`](OpenOptions::environment), or the current one.
+    pub(crate) fn runtime_environment(&self) -> &Environment {
+        self.environment
+            .as_ref()
+            .unwrap_or_else(|| Environment::current())
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    pub(crate) fn should_ask(&self) -> bool {
+        self.ask
+    }
+
+    #[cfg(all(feature = "portal", target_os = "linux"))]
+    pub(crate) fn should_allow_writes(&self) -> bool {
+        self.writable
+    }
+
+    /// Whether to wait for a launcher, given whether that launcher is waited for by default.
+    pub(crate) fn should_wait(&self, default: bool) -> bool {
+        self.wait.unwrap_or(default)
+    }
+
+    /// Returns the launchers in the order they should be tried: the preferred launcher (if any),
+    /// then either the ones set with [`launchers`](OpenOptions::launchers), or the custom backends
+    /// followed by the platform's `defaults`. Disabled, unsupported and unavailable custom
+    /// launchers are left out.
+    pub(crate) fn launcher_order(&self, defaults: &[Launcher]) -> Vec<Launcher> {
+        self.order(self.launchers.as_deref(), defaults, Launcher::is_supported)
+    }
+
+    /// Like [`launcher_order`](OpenOptions::launcher_order), for revealing paths and opening
+    /// folders, following [`reveal_launchers`](OpenOptions::reveal_launchers).
+    #[cfg(feature = "reveal")]
+    fn reveal_launcher_order(&self) -> Vec<Launcher> {
+        self.order(
+            self.reveal_launchers.as_deref(),
+            &sys::default_reveal_launchers(self),
+            Launcher::can_reveal,
+        )
+    }
+
+    fn order(
+        &self,
+        listed: Option<&[Launcher]>,
+        defaults: &[Launcher],
+        is_supported: fn(&Launcher) -> bool,
+    ) -> Vec<Launcher> {
+        let candidates: Vec<Launcher> = match listed {
+            Some(launchers) => launchers.to_vec(),
+            None => self
+                .backends
+                .iter()
+                .map(|backend| Launcher::Custom(backend.name().to_owned()))
+                .chain(defaults.iter().cloned())
+                .collect(),
+        };
+
+        let mut order = Vec::with_capacity(candidates.len() + 1);
+        for launcher in self.preferred.iter().chain(&candidates) {
+            let usable = match launcher {
+                Launcher::Custom(_) => self.custom_backend(launcher).is_some(),
+                launcher => is_supported(launcher),
+            };
+            if usable && !order.contains(launcher) && !self.disabled.contains(launcher) {
+                order.push(launcher.clone());
+            }
+        }
+        order
+    }
+
+    /// The registered and available custom backend for a [`Launcher::Custom`].
+    pub(crate) fn custom_backend(&self, launcher: &Launcher) -> Option<&dyn Backend> {
+        let Launcher::Custom(name) = launcher else {
+            return None;
+        };
+        self.backends
+            .iter()
+            .find(|backend| backend.name() == name && backend.is_available())
+            .map(|backend| &**backend)
+    }
+
+    /// Tries each launcher in order until one succeeds, running custom backends itself, and
+    /// built-in launchers with `builtin`. A cancelled request isn't passed on to the next
+    /// launcher, since the user already made their choice.
+    pub(crate) fn try_launchers<F>(
+        &self,
+        path: &OsStr,
+        defaults: &[Launcher],
+        mut builtin: F,
+    ) -> Result<Launched, OpenError>
+    where
+        F: FnMut(&Launcher) -> Result<Launched, OpenError>,
+    {
+        let mut attempts = Vec::new();
+        for launcher in self.launcher_order(defaults) {
+            let result = match self.custom_backend(&launcher) {
+                Some(backend) => backend.open(path, self),
+                None => builtin(&launcher),
+            };
+            match result {
+                Ok(launched) => return Ok(launched),
+                Err(OpenError::Cancelled) => return Err(OpenError::Cancelled),
+                Err(err) => attempts.push((launcher.to_string(), err)),
+            }
+        }
+        Err(OpenError::from_attempts(attempts))
+    }
+
+    /// Tries each launcher for revealing paths or opening folders in order until one succeeds,
+    /// running custom backends with `custom`, and built-in launchers with `builtin`. Launchers that
+    /// don't support the `operation` are skipped.
+    #[cfg(feature = "reveal")]
+    fn try_reveal_launchers<C, B>(
+        &self,
+        operation: &'static str,
+        mut custom: C,
+        mut builtin: B,
+    ) -> Result<(), OpenError>
+    where
+        C: FnMut(&dyn Backend) -> Result<(), OpenError>,
+        B: FnMut(&Launcher) -> Result<(), OpenError>,
+    {
+        let mut attempts = Vec::new();
+        for launcher in self.reveal_launcher_order() {
+            let result = match self.custom_backend(&launcher) {
+                Some(backend) => custom(backend),
+                None => builtin(&launcher),
+            };
+            match result {
+                Ok(()) => return Ok(()),
+                Err(OpenError::Unsupported { .. }) => {}
+                Err(OpenError::Cancelled) => return Err(OpenError::Cancelled),
+                Err(err) => attempts.push((launcher.to_string(), err)),
+            }
+        }
+        if attempts.is_empty() {
+            return Err(OpenError::Unsupported { operation });
+        }
+        Err(OpenError::from_attempts(attempts))
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Identifies a window of the calling program to the [XDG desktop portal], so that dialogs it
+/// shows, such as the application chooser, are modal to that window.
+///
+/// Set it with [`OpenOptions::parent_window`](crate::OpenOptions::parent_window). It's formatted as
+/// the portal expects, e.g. `x11:4a0001c` or `wayland:<handle>`.
+///
+/// [XDG desktop portal]: https://flatpak.github.io/xdg-desktop-portal/docs/window-identifiers.html
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WindowIdentifier {
+    /// An X11 window, by its XID.
+    X11(u64),
+
+    /// A Wayland toplevel surface, by a handle exported with the `xdg_foreign` protocol (version 2,
+    /// `zxdg_exporter_v2::export_toplevel`).
+    Wayland(String),
+}
+
+impl WindowIdentifier {
+    /// Gets the identifier of an X11 window from a window handle, such as a `winit` window.
+    ///
+    /// Returns `None` for other kinds of windows. Wayland surfaces have to be exported with the
+    /// `xdg_foreign` protocol by the caller, which requires its Wayland connection, before their
+    /// handle can be wrapped in [`WindowIdentifier::Wayland`].
+    #[cfg(feature = "raw-window-handle")]
+    pub fn from_window_handle<W>(window: &W) -> Option<Self>
+    where
+        W: raw_window_handle::HasWindowHandle + ?Sized,
+    {
+        use raw_window_handle::RawWindowHandle;
+
+        // XIDs are `c_ulong`, which is only 64-bit on some platforms.
+        #[allow(clippy::useless_conversion)]
+        match window.window_handle().ok()?.as_raw() {
+            RawWindowHandle::Xlib(handle) => Some(WindowIdentifier::X11(handle.window.into())),
+            RawWindowHandle::Xcb(handle) => Some(WindowIdentifier::X11(handle.window.get().into())),
+            _ => None,
+        }
+    }
+}
+
+impl Display for WindowIdentifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowIdentifier::X11(xid) => write!(f, "x11:{xid:x}"),
+            WindowIdentifier::Wayland(handle) => write!(f, "wayland:{handle}"),
+        }
+    }
+}
@@ -1,48 +1,191 @@
-use crate::OpenError;
+use crate::{Application, Launched, Launcher, OpenError, OpenOptions};
 use normpath::PathExt;
-use std::ffi::OsStr;
-use std::os::windows::ffi::OsStrExt;
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
 use std::path::PathBuf;
 use std::{io, ptr};
-use windows_sys::Win32::UI::Shell::ShellExecuteW;
+use windows_sys::Win32::Foundation::ERROR_CANCELLED;
+#[cfg(feature = "reveal")]
+use windows_sys::Win32::UI::Shell::{SHObjectProperties, SHOP_FILEPATH};
+use windows_sys::Win32::UI::Shell::{ShellExecuteW, SE_ERR_ASSOCINCOMPLETE, SE_ERR_NOASSOC};
 use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOW;
 
 #[cfg(feature = "reveal")]
 mod reveal;
-#[cfg(feature = "reveal")]
-pub(crate) use self::reveal::reveal;
 
-pub(crate) fn open(path: &OsStr) -> Result<(), OpenError> {
-    let Err(first_error) = open_helper(path) else {
-        return Ok(());
+pub(crate) fn default_launchers(_options: &OpenOptions) -> Vec<Launcher> {
+    vec![Launcher::ShellExecute]
+}
+
+pub(crate) fn is_launcher_available(launcher: &Launcher, _options: &OpenOptions) -> bool {
+    launcher.is_supported()
+}
+
+pub(crate) fn open(path: &OsStr, options: &OpenOptions) -> Result<Launched, OpenError> {
+    options.try_launchers(path, &default_launchers(options), |_| {
+        open_with_shell_execute(path, options)
+    })
+}
+
+fn open_with_shell_execute(path: &OsStr, options: &OpenOptions) -> Result<Launched, OpenError> {
+    let verb = if options.should_ask() {
+        "openas"
+    } else {
+        "open"
+    };
+    let Err(first_error) = open_helper(path, verb, options) else {
+        return Ok(shell_execute_launched());
     };
 
     match PathBuf::from(path).normalize() {
-        Ok(normalized) => match open_helper(normalized.as_os_str()) {
-            Ok(()) => Ok(()),
+        Ok(normalized) => match open_helper(normalized.as_os_str(), verb, options) {
+            Ok(()) => Ok(shell_execute_launched()),
             Err(_second_error) => Err(first_error),
         },
         Err(_) => Err(first_error),
     }
 }
 
-pub(crate) fn open_helper(path: &OsStr) -> Result<(), OpenError> {
+pub(crate) fn open_with(
+    path: &OsStr,
+    app: &OsStr,
+    options: &OpenOptions,
+) -> Result<Launched, OpenError> {
+    let app = convert_path(app).map_err(OpenError::Io)?;
+    let parameters = convert_path(&quote_arg(path)).map_err(OpenError::Io)?;
+    shell_execute(&app, Some(&parameters), "open", options)?;
+    Ok(shell_execute_launched())
+}
+
+/// The `openas` verb shows the "Open with" dialog for a file.
+pub(crate) fn open_with_chooser(
+    path: &OsStr,
+    options: &OpenOptions,
+) -> Result<Launched, OpenError> {
+    let path = convert_path(path).map_err(OpenError::Io)?;
+    shell_execute(&path, None, "openas", options)?;
+    Ok(shell_execute_launched())
+}
+
+/// Not implemented yet: the shell's association handlers would be needed to list the applications
+/// for a path.
+pub(crate) fn applications_for(_path: &OsStr) -> Result<Vec<Application>, OpenError> {
+    Ok(Vec::new())
+}
+
+/// `ShellExecuteW` reports whether the launch succeeded, but not which process (if any) was
+/// started.
+fn shell_execute_launched() -> Launched {
+    Launched::new(Launcher::ShellExecute, None, true)
+}
+
+#[cfg(feature = "reveal")]
+pub(crate) fn default_reveal_launchers(_options: &OpenOptions) -> Vec<Launcher> {
+    vec![Launcher::Explorer]
+}
+
+#[cfg(feature = "reveal")]
+pub(crate) fn reveal_with_launcher(
+    launcher: &Launcher,
+    paths: &[&std::path::Path],
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    match launcher {
+        Launcher::Explorer => reveal::reveal_many(paths, options),
+        _ => Err(OpenError::Unsupported {
+            operation: "revealing paths with this launcher",
+        }),
+    }
+}
+
+/// The `explore` verb always opens folders in File Explorer.
+#[cfg(feature = "reveal")]
+pub(crate) fn open_folder_with_launcher(
+    launcher: &Launcher,
+    path: &std::path::Path,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    match launcher {
+        Launcher::Explorer => {
+            let path = convert_path(path.as_os_str()).map_err(OpenError::Io)?;
+            shell_execute(&path, None, "explore", options)
+        }
+        _ => Err(OpenError::Unsupported {
+            operation: "opening folders with this launcher",
+        }),
+    }
+}
+
+#[cfg(feature = "reveal")]
+pub(crate) fn show_properties(
+    path: &std::path::Path,
+    _options: &OpenOptions,
+) -> Result<(), OpenError> {
+    let path = convert_path(path.as_os_str()).map_err(OpenError::Io)?;
+    let result = unsafe {
+        SHObjectProperties(
+            ptr::null_mut(),
+            SHOP_FILEPATH as u32,
+            path.as_ptr(),
+            ptr::null(),
+        )
+    };
+    if result == 0 {
+        Err(OpenError::Io(io::Error::last_os_error()))
+    } else {
+        Ok(())
+    }
+}
+
+pub(crate) fn open_helper(
+    path: &OsStr,
+    verb: &str,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
     let path = convert_path(path).map_err(OpenError::Io)?;
-    let operation: Vec<u16> = OsStr::new("open\0").encode_wide().collect();
+    shell_execute(&path, None, verb, options)
+}
+
+fn shell_execute(
+    file: &[u16],
+    parameters: Option<&[u16]>,
+    verb: &str,
+    options: &OpenOptions,
+) -> Result<(), OpenError> {
+    let directory = options
+        .current_dir_path()
+        .map(|dir| convert_path(dir.as_os_str()))
+        .transpose()
+        .map_err(OpenError::Io)?;
+    let operation: Vec<u16> = OsStr::new(verb).encode_wide().chain([0]).collect();
     let result = unsafe {
         ShellExecuteW(
             ptr::null_mut(),
             operation.as_ptr(),
-            path.as_ptr(),
-            ptr::null(),
-            ptr::null(),
+            file.as_ptr(),
+            parameters.map_or(ptr::null(), |params| params.as_ptr()),
+            directory.as_ref().map_or(ptr::null(), |dir| dir.as_ptr()),
             SW_SHOW,
         )
     };
-    if result as usize as isize > 32 {
-        Ok(())
+    let result = result as usize as isize;
+    if result > 32 {
+        return Ok(());
+    }
+
+    // These results aren't error codes, so the last OS error doesn't describe them.
+    if result == SE_ERR_NOASSOC as isize || result == SE_ERR_ASSOCINCOMPLETE as isize {
+        return Err(OpenError::NoHandler {
+            cmd: "ShellExecuteW".into(),
+            stderr: "no application is associated with this file type".into(),
+        });
+    }
+
+    let error = io::Error::last_os_error();
+    if error.raw_os_error() == Some(ERROR_CANCELLED as i32) {
+        Err(OpenError::Cancelled)
     } else {
-        Err(OpenError::Io(io::Error::last_os_error()))
+        Err(OpenError::Io(error))
     }
 }
 
@@ -58,3 +201,26 @@ fn convert_path(path: &OsStr) -> io::Result<Vec<u16>> {
     maybe_result.push(0);
     Ok(maybe_result)
 }
+
+/// Quotes a command line argument so that `CommandLineToArgvW` parses it back unchanged.
+fn quote_arg(arg: &OsStr) -> OsString {
+    let mut quoted: Vec<u16> = vec![u16::from(b'"')];
+    let mut backslashes = 0;
+    for c in arg.encode_wide() {
+        if c == u16::from(b'\\') {
+            backslashes += 1;
+        } else {
+            if c == u16::from(b'"') {
+                // Backslashes before a quote, and the quote itself, must be escaped.
+                quoted.resize(quoted.len() + backslashes + 1, u16::from(b'\\'));
+            }
+            backslashes = 0;
+        }
+        quoted.push(c);
+    }
+    // Backslashes before the closing quote must be escaped too.
+    quoted.resize(quoted.len() + backslashes, u16::from(b'\\'));
+    quoted.push(u16::from(b'"'));
+
+    OsString::from_wide(&quoted)
+}
@@ -1,49 +1,99 @@
 #![allow(non_camel_case_types, non_snake_case)]
 
 use super::convert_path;
-use crate::OpenError;
+use crate::{OpenError, OpenOptions};
 use normpath::PathExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{io, ptr, thread};
 use windows_sys::core::HRESULT;
 use windows_sys::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
-use windows_sys::Win32::UI::Shell::{ILCreateFromPathW, ILFree, SHOpenFolderAndSelectItems};
+use windows_sys::Win32::UI::Shell::Common::ITEMIDLIST;
+use windows_sys::Win32::UI::Shell::{
+    ILCreateFromPathW, ILFindLastID, ILFree, SHOpenFolderAndSelectItems,
+};
 
-pub(crate) fn reveal(path: &Path) -> Result<(), OpenError> {
-    let path = path.to_owned();
+pub(crate) fn reveal_many(paths: &[&Path], _options: &OpenOptions) -> Result<(), OpenError> {
+    let paths: Vec<PathBuf> = paths.iter().map(|path| path.to_path_buf()).collect();
     thread::Builder::new()
-        .spawn(move || reveal_in_thread(&path).map_err(OpenError::Io))
+        .spawn(move || reveal_in_thread(&paths).map_err(OpenError::Io))
         .map_err(OpenError::Io)?
         .join()
         .expect("COM worker thread should not panic")
 }
 
-fn reveal_in_thread(path: &Path) -> io::Result<()> {
+fn reveal_in_thread(paths: &[PathBuf]) -> io::Result<()> {
     unsafe {
         to_io_result(CoInitializeEx(ptr::null_mut(), COINIT_MULTITHREADED as u32))?;
     }
 
-    let item_id_list = {
-        // The ILCreateFromPathW function expects a canonicalized path.
-        // Unfortunately it does not support NT UNC paths (which std::path::canonicalize returns)
-        // so we use the normpath crate instead.
-        let path = convert_path(path.normalize()?.as_os_str())?;
+    let result = paths
+        .iter()
+        .map(|path| Ok(path.normalize()?.into_path_buf()))
+        .collect::<io::Result<Vec<_>>>()
+        .and_then(|paths| {
+            crate::group_by_parent(&paths)
+                .into_iter()
+                .try_for_each(|group| select_in_folder(&group))
+        });
+
+    unsafe {
+        CoUninitialize();
+    }
+
+    result
+}
+
+/// Opens the folder containing `items`, which all have the same parent, and selects them.
+fn select_in_folder(items: &[&PathBuf]) -> io::Result<()> {
+    let folder = match items[0].parent() {
+        Some(folder) if items.len() > 1 => folder,
+        // Drive roots have no parent folder to select them in, but can be selected on their own.
+        _ => {
+            return items.iter().try_for_each(|item| {
+                let item = ItemIdList::new(item)?;
+                // Because the cidl argument is zero, SHOpenFolderAndSelectItems opens the singular
+                // item in our item id list in its containing folder and selects it.
+                to_io_result(unsafe { SHOpenFolderAndSelectItems(item.0, 0, ptr::null(), 0) })
+            });
+        }
+    };
+
+    let folder = ItemIdList::new(folder)?;
+    let items = items
+        .iter()
+        .map(|item| ItemIdList::new(item))
+        .collect::<io::Result<Vec<_>>>()?;
+    // The last ID of each absolute item id list is the item relative to the folder.
+    let children: Vec<*const ITEMIDLIST> = items
+        .iter()
+        .map(|item| unsafe { ILFindLastID(item.0) } as *const ITEMIDLIST)
+        .collect();
+    to_io_result(unsafe {
+        SHOpenFolderAndSelectItems(folder.0, children.len() as u32, children.as_ptr(), 0)
+    })
+}
+
+/// An absolute item id list, freed when dropped.
+struct ItemIdList(*mut ITEMIDLIST);
+
+impl ItemIdList {
+    /// The ILCreateFromPathW function expects a canonicalized path. Unfortunately it does not
+    /// support NT UNC paths (which std::path::canonicalize returns), so paths are normalized with
+    /// the normpath crate instead.
+    fn new(path: &Path) -> io::Result<Self> {
+        let path = convert_path(path.as_os_str())?;
         let result = unsafe { ILCreateFromPathW(path.as_ptr()) };
         if result.is_null() {
-            return Err(io::Error::last_os_error());
+            Err(io::Error::last_os_error())
         } else {
-            result
+            Ok(Self(result))
         }
-    };
-
-    unsafe {
-        // Because the cidl argument is zero, SHOpenFolderAndSelectItems opens the singular item
-        // in our item id list in its containing folder and selects it.
-        let result = to_io_result(SHOpenFolderAndSelectItems(item_id_list, 0, ptr::null(), 0));
-        ILFree(item_id_list);
-        CoUninitialize();
+    }
+}
 
-        result
+impl Drop for ItemIdList {
+    fn drop(&mut self) {
+        unsafe { ILFree(self.0) }
     }
 }
 
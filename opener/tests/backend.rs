@@ -0,0 +1,184 @@
+//! Checks that custom backends are used for opening and revealing paths.
+
+use opener::{Backend, Launched, Launcher, OpenError, OpenOptions};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default, Clone)]
+struct Recorder {
+    name: &'static str,
+    fail: bool,
+    opened: Arc<Mutex<Vec<PathBuf>>>,
+    revealed: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl Backend for Recorder {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn open(&self, path: &OsStr, _options: &OpenOptions) -> Result<Launched, OpenError> {
+        if self.fail {
+            return Err(OpenError::PortalFailed);
+        }
+        self.opened.lock().unwrap().push(path.into());
+        Ok(Launched::new(
+            Launcher::Custom(self.name.to_owned()),
+            None,
+            true,
+        ))
+    }
+
+    fn reveal(&self, paths: &[&Path], _options: &OpenOptions) -> Result<(), OpenError> {
+        if self.fail {
+            return Err(OpenError::PortalFailed);
+        }
+        self.revealed
+            .lock()
+            .unwrap()
+            .extend(paths.iter().map(|path| path.to_path_buf()));
+        Ok(())
+    }
+}
+
+#[test]
+fn open_uses_backend_first() {
+    let backend = Recorder {
+        name: "recorder",
+        ..Recorder::default()
+    };
+    let launched = OpenOptions::new()
+        .backend(backend.clone())
+        .open("report.pdf")
+        .unwrap();
+    assert_eq!(launched.launcher(), &Launcher::Custom("recorder".into()));
+    assert_eq!(
+        *backend.opened.lock().unwrap(),
+        [PathBuf::from("report.pdf")]
+    );
+}
+
+#[test]
+fn open_falls_through_failed_backends() {
+    let failing = Recorder {
+        name: "failing",
+        fail: true,
+        ..Recorder::default()
+    };
+    let working = Recorder {
+        name: "working",
+        ..Recorder::default()
+    };
+    let launched = OpenOptions::new()
+        .backend(failing)
+        .backend(working.clone())
+        .launchers([
+            Launcher::Custom("failing".into()),
+            Launcher::Custom("working".into()),
+        ])
+        .open("report.pdf")
+        .unwrap();
+    assert_eq!(launched.launcher(), &Launcher::Custom("working".into()));
+}
+
+#[test]
+fn open_reports_every_failure() {
+    let err = OpenOptions::new()
+        .backend(Recorder {
+            name: "a",
+            fail: true,
+            ..Recorder::default()
+        })
+        .backend(Recorder {
+            name: "b",
+            fail: true,
+            ..Recorder::default()
+        })
+        .launchers([Launcher::Custom("a".into()), Launcher::Custom("b".into())])
+        .open("report.pdf")
+        .unwrap_err();
+    match err {
+        OpenError::AllFailed { attempts, .. } => {
+            let names: Vec<_> = attempts.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(names, ["a", "b"]);
+        }
+        err => panic!("unexpected error: {err:?}"),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn open_explains_failures_without_display() {
+    use opener::{Environment, OpenErrorKind};
+
+    let mut environment = Environment::new();
+    environment.set_headless(true);
+    let err = OpenOptions::new()
+        .environment(environment)
+        .backend(Recorder {
+            name: "a",
+            fail: true,
+            ..Recorder::default()
+        })
+        .backend(Recorder {
+            name: "b",
+            fail: true,
+            ..Recorder::default()
+        })
+        .launchers([Launcher::Custom("a".into()), Launcher::Custom("b".into())])
+        .open("report.pdf")
+        .unwrap_err();
+    assert_eq!(err.kind(), OpenErrorKind::NoDisplay);
+    assert!(err.hint().unwrap().contains("DISPLAY"), "{err:#}");
+}
+
+#[cfg(feature = "reveal")]
+#[test]
+fn reveal_uses_backend() {
+    let backend = Recorder {
+        name: "recorder",
+        ..Recorder::default()
+    };
+    let options = {
+        let mut options = OpenOptions::new();
+        options.backend(backend.clone());
+        options
+    };
+    options.reveal("Cargo.toml").unwrap();
+    options.reveal_many(["src", "tests"]).unwrap();
+    assert_eq!(
+        *backend.revealed.lock().unwrap(),
+        [
+            PathBuf::from("Cargo.toml"),
+            PathBuf::from("src"),
+            PathBuf::from("tests")
+        ]
+    );
+}
+
+#[cfg(feature = "reveal")]
+#[test]
+fn reveal_launchers_replace_builtin_ones() {
+    let err = OpenOptions::new()
+        .backend(Recorder {
+            name: "recorder",
+            fail: true,
+            ..Recorder::default()
+        })
+        .reveal_launchers([Launcher::Custom("recorder".into())])
+        .reveal("Cargo.toml")
+        .unwrap_err();
+    // Only the backend was tried, so its error is returned as it is.
+    assert!(matches!(err, OpenError::PortalFailed), "{err:?}");
+}
+
+#[cfg(feature = "reveal")]
+#[test]
+fn reveal_without_launchers_is_unsupported() {
+    let err = OpenOptions::new()
+        .reveal_launchers([])
+        .reveal("Cargo.toml")
+        .unwrap_err();
+    assert!(matches!(err, OpenError::Unsupported { .. }), "{err:?}");
+}
@@ -0,0 +1,30 @@
+#![allow(dead_code)]
+
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Writes fake programs, given by name and script, to a directory named `dir`, and returns a `PATH`
+/// that puts them first, to be passed to the launcher with `OpenOptions::env`. The test process's
+/// own `PATH` isn't changed, since other tests may be reading it at the same time.
+pub fn fake_programs(dir: &str, programs: &[(&str, &str)]) -> OsString {
+    let bin = Path::new(env!("CARGO_TARGET_TMPDIR")).join(dir);
+    fs::create_dir_all(&bin).unwrap();
+    for (name, script) in programs {
+        let program = bin.join(name);
+        fs::write(&program, script).unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut paths = vec![bin];
+    paths.extend(std::env::split_paths(&path));
+    std::env::join_paths(paths).unwrap()
+}
+
+/// Writes a fake `xdg-open` running `script` to a directory named `dir`, and returns a `PATH` that
+/// puts it first.
+pub fn fake_xdg_open(dir: &str, script: &str) -> OsString {
+    fake_programs(dir, &[("xdg-open", script)])
+}
@@ -0,0 +1,151 @@
+//! Checks the parsing and expansion of the `Exec` key of desktop entries.
+
+#![cfg(target_os = "linux")]
+
+use opener::DesktopEntry;
+use std::ffi::OsString;
+use std::path::Path;
+use std::{fs, io};
+
+fn entry(exec: &str) -> DesktopEntry {
+    DesktopEntry::parse(&format!(
+        "[Desktop Entry]\nType=Application\nName=Viewer\nExec={exec}\n"
+    ))
+}
+
+fn expand(entry: &DesktopEntry, targets: &[&str]) -> Vec<Vec<String>> {
+    entry
+        .expand_exec(targets)
+        .unwrap()
+        .into_iter()
+        .map(|argv| {
+            argv.into_iter()
+                .map(|arg| arg.into_string().unwrap())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn splits_quoted_arguments() {
+    let entry = entry(r#""/opt/my app/bin"  --title "say \"hi\"" "" 'single'"#);
+    assert_eq!(
+        expand(&entry, &[]),
+        [["/opt/my app/bin", "--title", r#"say "hi""#, "", "'single'"]]
+    );
+}
+
+#[test]
+fn rejects_unbalanced_quotes() {
+    let err = entry(r#""/opt/app %f"#).expand_exec(["a"]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn rejects_missing_exec() {
+    let entry = DesktopEntry::parse("[Desktop Entry]\nName=Viewer\n");
+    assert!(entry.expand_exec(["a"]).is_err());
+}
+
+#[test]
+fn single_target_codes_launch_one_instance_per_target() {
+    for code in ["%f", "%u"] {
+        let entry = entry(&format!("viewer --open {code}"));
+        assert_eq!(
+            expand(&entry, &["a", "b"]),
+            [["viewer", "--open", "a"], ["viewer", "--open", "b"]]
+        );
+        assert_eq!(expand(&entry, &[]), [["viewer", "--open"]]);
+    }
+}
+
+#[test]
+fn list_codes_expand_to_all_targets() {
+    for code in ["%F", "%U"] {
+        let entry = entry(&format!("viewer {code} --end"));
+        assert_eq!(expand(&entry, &["a", "b"]), [["viewer", "a", "b", "--end"]]);
+        assert_eq!(expand(&entry, &[]), [["viewer", "--end"]]);
+    }
+}
+
+#[test]
+fn list_codes_inside_arguments_expand_to_one_target() {
+    let entry = entry("viewer --files=%F");
+    assert_eq!(
+        expand(&entry, &["a", "b"]),
+        [["viewer", "--files=a"], ["viewer", "--files=b"]]
+    );
+}
+
+#[test]
+fn targets_are_appended_without_file_codes() {
+    let entry = entry("viewer --new-window");
+    assert_eq!(
+        expand(&entry, &["a", "b"]),
+        [["viewer", "--new-window", "a", "b"]]
+    );
+}
+
+#[test]
+fn icon_code() {
+    let with_icon =
+        DesktopEntry::parse("[Desktop Entry]\nName=Viewer\nIcon=viewer-icon\nExec=viewer %i %f\n");
+    assert_eq!(
+        expand(&with_icon, &["a"]),
+        [["viewer", "--icon", "viewer-icon", "a"]]
+    );
+    assert_eq!(expand(&entry("viewer %i %f"), &["a"]), [["viewer", "a"]]);
+}
+
+#[test]
+fn name_and_location_codes() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("desktop-entry-location");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("viewer.desktop");
+    fs::write(
+        &path,
+        "[Desktop Entry]\nName=Viewer\nExec=viewer --class=%c %k %f\n",
+    )
+    .unwrap();
+
+    let entry = DesktopEntry::from_path(&path).unwrap();
+    assert_eq!(
+        expand(&entry, &["a"]),
+        [vec![
+            "viewer".to_owned(),
+            "--class=Viewer".to_owned(),
+            path.to_str().unwrap().to_owned(),
+            "a".to_owned(),
+        ]]
+    );
+    // Without a file, `%k` expands to nothing.
+    assert_eq!(
+        expand(&self::entry("viewer %k %f"), &["a"]),
+        [["viewer", "a"]]
+    );
+}
+
+#[test]
+fn percent_escapes_are_replaced_inside_and_outside_quotes() {
+    let entry = entry(r#""/opt/my%%app/bin" 100%% %f"#);
+    assert_eq!(expand(&entry, &["a"]), [["/opt/my%app/bin", "100%", "a"]]);
+}
+
+#[test]
+fn deprecated_codes_are_removed() {
+    let entry = entry("viewer %d %D %n %N %v %m %f");
+    assert_eq!(expand(&entry, &["a"]), [["viewer", "a"]]);
+}
+
+#[test]
+fn expanded_targets_are_not_split() {
+    let entry = entry("viewer %f");
+    let argv = entry.expand_exec(["with space \"and quotes\""]).unwrap();
+    assert_eq!(
+        argv,
+        [[
+            OsString::from("viewer"),
+            OsString::from("with space \"and quotes\"")
+        ]]
+    );
+}
@@ -0,0 +1,29 @@
+//! Checks that `DesktopEntry::find` skips hidden entries. This sets `XDG_DATA_HOME`, so it's kept
+//! in its own test binary, where no other test reads the environment at the same time.
+
+#![cfg(target_os = "linux")]
+
+use opener::DesktopEntry;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn hidden_entries_are_not_found() {
+    let data_home = Path::new(env!("CARGO_TARGET_TMPDIR")).join("desktop-entry-data");
+    let applications = data_home.join("applications");
+    fs::create_dir_all(&applications).unwrap();
+    fs::write(
+        applications.join("opener-test-shown.desktop"),
+        "[Desktop Entry]\nName=Shown\nExec=shown %f\n",
+    )
+    .unwrap();
+    fs::write(
+        applications.join("opener-test-hidden.desktop"),
+        "[Desktop Entry]\nName=Hidden\nExec=hidden %f\nHidden=true\n",
+    )
+    .unwrap();
+    std::env::set_var("XDG_DATA_HOME", &data_home);
+
+    assert!(DesktopEntry::find("opener-test-shown").is_some());
+    assert!(DesktopEntry::find("opener-test-hidden").is_none());
+}
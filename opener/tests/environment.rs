@@ -0,0 +1,69 @@
+//! Checks that an `Environment` passed to `OpenOptions` is used instead of the detected one.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use opener::{DesktopEnvironment, Environment, Launcher, OpenOptions, Package, WslVersion};
+use std::ffi::OsString;
+use std::sync::OnceLock;
+
+const EXIT_0: &str = "#!/bin/sh\nexit 0\n";
+
+fn options(environment: Environment) -> OpenOptions {
+    // The tests run on several threads, and the programs mustn't be rewritten while one runs.
+    static PATH: OnceLock<OsString> = OnceLock::new();
+    let path = PATH.get_or_init(|| {
+        common::fake_programs(
+            "environment-bin",
+            &[("xdg-open", EXIT_0), ("wslview", EXIT_0)],
+        )
+    });
+    let mut options = OpenOptions::new();
+    options.environment(environment).env("PATH", path);
+    options
+}
+
+#[test]
+fn wsl_uses_wslview() {
+    let mut environment = Environment::new();
+    environment.set_wsl(Some(WslVersion::Wsl1));
+    let options = options(environment);
+    assert_eq!(
+        options.available_launchers(),
+        [Launcher::WslView, Launcher::XdgOpen]
+    );
+
+    let launched = options.open("https://example.com").unwrap();
+    assert_eq!(launched.launcher(), &Launcher::WslView);
+}
+
+#[test]
+fn native_environment_uses_xdg_open() {
+    let options = options(Environment::new());
+    let launchers = options.available_launchers();
+    assert!(!launchers.contains(&Launcher::WslView), "{launchers:?}");
+    assert!(launchers.contains(&Launcher::XdgOpen), "{launchers:?}");
+}
+
+#[test]
+fn sandbox_uses_portal_first() {
+    let mut environment = Environment::new();
+    environment.set_package(Some(Package::Flatpak));
+    let launchers = options(environment).available_launchers();
+    if cfg!(feature = "portal") {
+        assert_eq!(launchers.first(), Some(&Launcher::Portal));
+    } else {
+        assert!(!launchers.contains(&Launcher::Portal), "{launchers:?}");
+    }
+}
+
+#[test]
+fn flatpak_desktop_environment() {
+    let mut environment = Environment::new();
+    environment.set_package(Some(Package::Flatpak));
+    assert_eq!(
+        DesktopEnvironment::detect(&environment),
+        Some(DesktopEnvironment::Flatpak)
+    );
+}
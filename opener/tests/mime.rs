@@ -0,0 +1,29 @@
+//! Checks MIME type detection for files that don't depend on the installed database.
+
+#![cfg(target_os = "linux")]
+
+use opener::OpenErrorKind;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn special_files() {
+    assert_eq!(opener::mime_type("/").unwrap(), "inode/directory");
+    assert_eq!(opener::mime_type("/dev/null").unwrap(), "inode/chardevice");
+}
+
+#[test]
+fn empty_file_without_extension() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("mime");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("empty-file-without-extension");
+    fs::write(&path, "").unwrap();
+
+    assert_eq!(opener::mime_type(&path).unwrap(), "application/x-zerosize");
+}
+
+#[test]
+fn missing_file() {
+    let err = opener::mime_type("/nonexistent/opener-test").unwrap_err();
+    assert_eq!(err.kind(), OpenErrorKind::NotFound);
+}
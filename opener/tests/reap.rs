@@ -0,0 +1,52 @@
+//! Checks that launcher processes that aren't waited for don't linger as zombies.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use opener::{Launcher, OpenOptions};
+use std::time::{Duration, Instant};
+use std::{fs, thread};
+
+/// The children of this process that have exited but haven't been waited for.
+fn zombie_children() -> Vec<u32> {
+    let ppid = std::process::id().to_string();
+    fs::read_dir("/proc")
+        .unwrap()
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let stat = fs::read_to_string(entry.path().join("stat")).ok()?;
+            // The command name may contain spaces and parentheses, so fields are counted from the
+            // last parenthesis.
+            let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace();
+            let state = fields.next()?;
+            (state == "Z" && fields.next()? == ppid).then_some(pid)
+        })
+        .collect()
+}
+
+#[test]
+fn no_zombies_after_repeated_opens() {
+    const OPENS: usize = 20;
+
+    let path = common::fake_xdg_open("reap-bin", "#!/bin/sh\necho opened \"$1\" >&2\nexit 0\n");
+
+    let mut options = OpenOptions::new();
+    options
+        .launchers([Launcher::XdgOpen])
+        .env("PATH", path)
+        .wait(false);
+    for _ in 0..OPENS {
+        options.open("https://example.com").unwrap();
+    }
+
+    // The launchers are reaped on background threads, so give them a moment.
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut zombies = zombie_children();
+    while !zombies.is_empty() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(50));
+        zombies = zombie_children();
+    }
+    assert!(zombies.is_empty(), "zombie children: {zombies:?}");
+}
@@ -0,0 +1,34 @@
+//! Checks that waiting for a launcher doesn't wait for programs that inherited its stderr.
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use opener::{Launcher, OpenError, OpenOptions};
+use std::time::{Duration, Instant};
+
+#[test]
+fn wait_returns_while_launched_program_logs() {
+    // The "launched program" keeps writing to the inherited stderr for a while after xdg-open has
+    // failed.
+    let path = common::fake_xdg_open(
+        "wait-bin",
+        "#!/bin/sh\n\
+         (i=0; while [ $i -lt 100 ]; do echo log >&2; sleep 0.05; i=$((i+1)); done) &\n\
+         echo failed >&2\n\
+         exit 4\n",
+    );
+
+    let start = Instant::now();
+    let err = OpenOptions::new()
+        .launchers([Launcher::XdgOpen])
+        .env("PATH", path)
+        .wait(true)
+        .open("https://example.com")
+        .unwrap_err();
+    assert!(start.elapsed() < Duration::from_secs(2), "{err}");
+    match err {
+        OpenError::ActionFailed { stderr, .. } => assert!(stderr.contains("failed"), "{stderr}"),
+        err => panic!("unexpected error: {err:?}"),
+    }
+}